@@ -1,14 +1,87 @@
-#[derive(Debug)]
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+
 pub enum HttpBody {
     Empty,
     Content(Vec<u8>),
+    /// A body of unknown length that is read and sent on demand using
+    /// chunked transfer-encoding.
+    Stream(Box<dyn Read + Send>),
+    /// The first `len` bytes of a file, copied straight to the connection.
+    /// On Linux the standard library does this with `sendfile`.
+    File { file: File, len: u64 },
 }
 
 impl HttpBody {
-    pub const fn len(&self) -> usize {
+    /// Creates a streaming body that yields the given chunks in order.
+    pub fn from_chunks<I>(chunks: I) -> Self
+    where
+        I: Iterator<Item = Vec<u8>> + Send + 'static,
+    {
+        Self::Stream(Box::new(ChunkReader {
+            chunks,
+            current: Vec::new(),
+            pos: 0,
+        }))
+    }
+
+    /// Returns the length of the body in bytes, or None if the body is
+    /// streamed and its length is not known in advance.
+    #[must_use]
+    pub fn len(&self) -> Option<usize> {
         match self {
-            Self::Empty => 0,
-            Self::Content(bytes) => bytes.len(),
+            Self::Empty => Some(0),
+            Self::Content(bytes) => Some(bytes.len()),
+            Self::Stream(_) => None,
+            Self::File { len, .. } => usize::try_from(*len).ok(),
         }
     }
+
+    /// Returns true if the body is known to have no bytes. A streamed body
+    /// may turn out empty, but isn't known to be.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+}
+
+impl fmt::Debug for HttpBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Empty"),
+            Self::Content(bytes) => f.debug_tuple("Content").field(bytes).finish(),
+            Self::Stream(_) => write!(f, "Stream(..)"),
+            Self::File { len, .. } => f.debug_struct("File").field("len", len).finish(),
+        }
+    }
+}
+
+/// Adapts an iterator of byte chunks into a `Read`.
+struct ChunkReader<I> {
+    chunks: I,
+    current: Vec<u8>,
+    pos: usize,
+}
+
+impl<I> Read for ChunkReader<I>
+where
+    I: Iterator<Item = Vec<u8>>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos >= self.current.len() {
+            match self.chunks.next() {
+                Some(chunk) => {
+                    self.current = chunk;
+                    self.pos = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+
+        let n = std::cmp::min(buf.len(), self.current.len() - self.pos);
+        buf[..n].copy_from_slice(&self.current[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
 }
@@ -0,0 +1,151 @@
+/*
+ * This module decides which responses are worth compressing.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use flate2::Compression;
+
+use crate::response::Response;
+
+/// How hard the content codings try to shrink a body. Each level maps to a
+/// coding-specific setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CompressionLevel {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+impl CompressionLevel {
+    #[must_use]
+    pub const fn flate(self) -> Compression {
+        match self {
+            Self::Fast => Compression::fast(),
+            Self::Default => Compression::new(6),
+            Self::Best => Compression::best(),
+        }
+    }
+
+    /// Brotli's default quality of 11 is too slow for on-the-fly use; 5
+    /// compresses about as well as gzip's default level.
+    #[must_use]
+    pub const fn brotli(self) -> u32 {
+        match self {
+            Self::Fast => 1,
+            Self::Default => 5,
+            Self::Best => 11,
+        }
+    }
+
+    #[must_use]
+    pub const fn zstd(self) -> i32 {
+        match self {
+            Self::Fast => 1,
+            Self::Default => 3,
+            Self::Best => 19,
+        }
+    }
+}
+
+/// Media types compressed by default. A `type/*` entry matches any subtype.
+const DEFAULT_COMPRESSIBLE_TYPES: &[&str] = &[
+    "text/*",
+    "application/json",
+    "application/javascript",
+    "application/xml",
+    "application/xhtml+xml",
+    "application/manifest+json",
+    "application/wasm",
+    "image/svg+xml",
+];
+
+/// Decides whether a response body is compressed and how hard.
+///
+/// Bodies below `min_size`, media types outside `content_types` (such as
+/// images and archives, which are compressed already), responses to paths
+/// under one of `excluded_routes` and responses that opted out with
+/// `Response::disable_compression` are sent as is.
+#[derive(Debug, Clone)]
+pub struct CompressionPolicy {
+    pub enabled: bool,
+    /// The smallest body in bytes worth compressing. Below this the coding
+    /// overhead outweighs the savings. Streamed bodies of unknown length
+    /// are always considered large enough.
+    pub min_size: usize,
+    pub content_types: Vec<String>,
+    pub level: CompressionLevel,
+    /// Path prefixes whose responses are never compressed.
+    pub excluded_routes: Vec<String>,
+}
+
+impl Default for CompressionPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size: 256,
+            content_types: DEFAULT_COMPRESSIBLE_TYPES
+                .iter()
+                .map(|t| String::from(*t))
+                .collect(),
+            level: CompressionLevel::Default,
+            excluded_routes: Vec::new(),
+        }
+    }
+}
+
+impl CompressionPolicy {
+    /// Returns true if the response to a request for `path` should be
+    /// compressed.
+    #[must_use]
+    pub fn allows(&self, path: &str, resp: &Response) -> bool {
+        self.allows_path(path)
+            && resp.is_compressible()
+            && resp.body_len().is_none_or(|len| len >= self.min_size)
+            && resp
+                .content_type()
+                .is_some_and(|content_type| self.is_compressible_type(content_type))
+    }
+
+    /// Returns true if responses to requests for `path` may be sent with a
+    /// content coding at all, whatever their body.
+    #[must_use]
+    pub fn allows_path(&self, path: &str) -> bool {
+        self.enabled
+            && !self
+                .excluded_routes
+                .iter()
+                .any(|route| route_matches(route, path))
+    }
+
+    /// Returns true if the media type, ignoring parameters such as charset,
+    /// is on the compressible list.
+    #[must_use]
+    pub fn is_compressible_type(&self, content_type: &str) -> bool {
+        let media_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+
+        self.content_types.iter().any(|pattern| {
+            let pattern = pattern.trim().to_lowercase();
+            pattern.strip_suffix("/*").map_or(pattern == media_type, |top| {
+                media_type
+                    .split_once('/')
+                    .is_some_and(|(media_top, _)| media_top == top)
+            })
+        })
+    }
+}
+
+/// A route matches the path itself and everything below it, so `/files`
+/// matches `/files` and `/files/a.txt` but not `/filesystem`.
+fn route_matches(route: &str, path: &str) -> bool {
+    let route = route.trim_end_matches('/');
+    path.strip_prefix(route)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
@@ -0,0 +1,106 @@
+use super::{CompressionLevel, CompressionPolicy};
+use crate::http::status::HttpStatus;
+use crate::response::Response;
+
+fn text_response(len: usize) -> Response {
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_str_body(&"a".repeat(len));
+    resp
+}
+
+#[test]
+fn test_default_policy_allows_large_text() {
+    let policy = CompressionPolicy::default();
+
+    assert!(policy.allows("/echo/x", &text_response(1024)));
+}
+
+#[test]
+fn test_policy_respects_min_size() {
+    let policy = CompressionPolicy {
+        min_size: 100,
+        ..Default::default()
+    };
+
+    assert!(!policy.allows("/echo/x", &text_response(99)));
+    assert!(policy.allows("/echo/x", &text_response(100)));
+}
+
+#[test]
+fn test_policy_allows_streams_of_unknown_length() {
+    let policy = CompressionPolicy::default();
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_stream_body("text/plain", std::io::Cursor::new(b"short"));
+
+    assert!(policy.allows("/", &resp));
+}
+
+#[test]
+fn test_policy_checks_content_type() {
+    let policy = CompressionPolicy {
+        min_size: 0,
+        ..Default::default()
+    };
+    let mut resp = Response::new(HttpStatus::Ok);
+
+    resp.set_bytes_body("image/png", b"\x89PNG");
+    assert!(!policy.allows("/files/a.png", &resp));
+
+    resp.set_bytes_body("application/zip", b"PK");
+    assert!(!policy.allows("/files/a.zip", &resp));
+
+    resp.set_bytes_body("application/json; charset=utf-8", b"{}");
+    assert!(policy.allows("/files/a.json", &resp));
+
+    resp.set_bytes_body("Text/HTML", b"<p>");
+    assert!(policy.allows("/files/a.html", &resp));
+}
+
+#[test]
+fn test_is_compressible_type() {
+    let policy = CompressionPolicy {
+        content_types: vec![String::from("text/*"), String::from("application/json")],
+        ..Default::default()
+    };
+
+    assert!(policy.is_compressible_type("text/plain"));
+    assert!(policy.is_compressible_type("text/css; charset=utf-8"));
+    assert!(policy.is_compressible_type("application/json"));
+    assert!(!policy.is_compressible_type("application/jsonx"));
+    assert!(!policy.is_compressible_type("textual/plain"));
+    assert!(!policy.is_compressible_type("image/svg+xml"));
+}
+
+#[test]
+fn test_policy_excluded_routes() {
+    let policy = CompressionPolicy {
+        min_size: 0,
+        excluded_routes: vec![String::from("/files/")],
+        ..Default::default()
+    };
+
+    assert!(!policy.allows("/files", &text_response(10)));
+    assert!(!policy.allows("/files/a.txt", &text_response(10)));
+    assert!(policy.allows("/filesystem", &text_response(10)));
+    assert!(policy.allows("/echo/a", &text_response(10)));
+}
+
+#[test]
+fn test_policy_respects_response_opt_out_and_disabled() {
+    let mut resp = text_response(1024);
+    resp.disable_compression();
+    assert!(!CompressionPolicy::default().allows("/", &resp));
+
+    let policy = CompressionPolicy {
+        enabled: false,
+        ..Default::default()
+    };
+    assert!(!policy.allows("/", &text_response(1024)));
+}
+
+#[test]
+fn test_compression_level_settings() {
+    assert!(CompressionLevel::Fast.flate().level() < CompressionLevel::Best.flate().level());
+    assert!(CompressionLevel::Fast.brotli() < CompressionLevel::Best.brotli());
+    assert!(CompressionLevel::Fast.zstd() < CompressionLevel::Best.zstd());
+}
@@ -0,0 +1,171 @@
+/*
+ * This module evaluates conditional request headers (If-Match, If-None-Match,
+ * If-Modified-Since and If-Unmodified-Since) against a representation's
+ * validators.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::time::SystemTime;
+
+use httpdate::HttpDate;
+
+use crate::consts;
+use crate::header::Headers;
+use crate::http::method::HttpMethod;
+
+/// An entity tag as used in the `ETag` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityTag {
+    pub weak: bool,
+    /// The opaque tag without the surrounding quotes.
+    pub tag: String,
+}
+
+impl EntityTag {
+    #[must_use]
+    pub fn strong(tag: &str) -> Self {
+        Self {
+            weak: false,
+            tag: String::from(tag),
+        }
+    }
+
+    #[must_use]
+    pub fn weak(tag: &str) -> Self {
+        Self {
+            weak: true,
+            tag: String::from(tag),
+        }
+    }
+
+    /// Parses a single entity tag such as `"abc"` or `W/"abc"`.
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (weak, quoted) = s.strip_prefix("W/").map_or((false, s), |rest| (true, rest));
+        let tag = quoted.strip_prefix('"')?.strip_suffix('"')?;
+
+        if tag.contains('"') {
+            return None;
+        }
+
+        Some(Self {
+            weak,
+            tag: String::from(tag),
+        })
+    }
+
+    /// Two tags match strongly if neither is weak and the tags are equal.
+    #[must_use]
+    pub fn strong_eq(&self, other: &Self) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Two tags match weakly if the tags are equal, regardless of weakness.
+    #[must_use]
+    pub fn weak_eq(&self, other: &Self) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl fmt::Display for EntityTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            write!(f, "W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+/// The validators of the selected representation.
+#[derive(Debug, Clone, Default)]
+pub struct Validators {
+    pub etag: Option<EntityTag>,
+    pub last_modified: Option<SystemTime>,
+}
+
+/// The outcome of evaluating the preconditions of a request.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Precondition {
+    /// The request should be processed normally.
+    Proceed,
+    /// The client's cached copy is current; respond with 304.
+    NotModified,
+    /// A precondition failed; respond with 412.
+    Failed,
+}
+
+/// Evaluates the conditional headers of a request for an existing
+/// representation, in the order defined by RFC 9110 section 13.2.2.
+pub fn evaluate(method: HttpMethod, headers: &Headers, validators: &Validators) -> Precondition {
+    if let Some(value) = headers.get(consts::HEADER_IF_MATCH) {
+        if !list_matches(value, validators.etag.as_ref(), EntityTag::strong_eq) {
+            return Precondition::Failed;
+        }
+    } else if let Some(since) = headers
+        .get(consts::HEADER_IF_UNMODIFIED_SINCE)
+        .and_then(parse_date)
+        && validators
+            .last_modified
+            .is_some_and(|m| HttpDate::from(m) > since)
+    {
+        return Precondition::Failed;
+    }
+
+    let safe = matches!(method, HttpMethod::Get | HttpMethod::Head);
+
+    if let Some(value) = headers.get(consts::HEADER_IF_NONE_MATCH) {
+        if list_matches(value, validators.etag.as_ref(), EntityTag::weak_eq) {
+            return if safe {
+                Precondition::NotModified
+            } else {
+                Precondition::Failed
+            };
+        }
+    } else if safe
+        && let Some(since) = headers
+            .get(consts::HEADER_IF_MODIFIED_SINCE)
+            .and_then(parse_date)
+        && validators
+            .last_modified
+            .is_some_and(|m| HttpDate::from(m) <= since)
+    {
+        return Precondition::NotModified;
+    }
+
+    Precondition::Proceed
+}
+
+/// Checks an If-Match or If-None-Match value against the current entity tag.
+/// `*` matches any existing representation.
+fn list_matches(
+    value: &str,
+    current: Option<&EntityTag>,
+    eq: fn(&EntityTag, &EntityTag) -> bool,
+) -> bool {
+    if value.trim() == "*" {
+        return true;
+    }
+
+    let Some(current) = current else {
+        return false;
+    };
+
+    value
+        .split(',')
+        .filter_map(EntityTag::parse)
+        .any(|tag| eq(&tag, current))
+}
+
+fn parse_date(value: &str) -> Option<HttpDate> {
+    value.trim().parse::<HttpDate>().ok()
+}
+
+/// Formats a time as an HTTP-date for the Last-Modified header.
+#[must_use]
+pub fn http_date(time: SystemTime) -> String {
+    httpdate::fmt_http_date(time)
+}
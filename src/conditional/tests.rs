@@ -0,0 +1,143 @@
+use super::*;
+
+use std::time::{Duration, UNIX_EPOCH};
+
+fn headers(lines: &[&str]) -> Headers {
+    let mut headers = Headers::new();
+    for line in lines {
+        headers.read(line.as_bytes()).unwrap();
+    }
+    headers
+}
+
+fn validators() -> Validators {
+    Validators {
+        etag: Some(EntityTag::strong("v1")),
+        last_modified: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+    }
+}
+
+fn date(secs: u64) -> String {
+    httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+// Tests for EntityTag
+#[test]
+fn test_entity_tag_parse_strong() {
+    assert_eq!(EntityTag::parse("\"abc\""), Some(EntityTag::strong("abc")));
+}
+
+#[test]
+fn test_entity_tag_parse_weak() {
+    assert_eq!(EntityTag::parse(" W/\"abc\" "), Some(EntityTag::weak("abc")));
+}
+
+#[test]
+fn test_entity_tag_parse_invalid() {
+    assert_eq!(EntityTag::parse("abc"), None);
+    assert_eq!(EntityTag::parse("\"abc"), None);
+    assert_eq!(EntityTag::parse("w/\"abc\""), None);
+}
+
+#[test]
+fn test_entity_tag_display() {
+    assert_eq!(EntityTag::strong("abc").to_string(), "\"abc\"");
+    assert_eq!(EntityTag::weak("abc").to_string(), "W/\"abc\"");
+}
+
+#[test]
+fn test_entity_tag_comparison() {
+    let strong = EntityTag::strong("abc");
+    let weak = EntityTag::weak("abc");
+
+    assert!(strong.strong_eq(&strong));
+    assert!(!strong.strong_eq(&weak));
+    assert!(!weak.strong_eq(&weak));
+    assert!(strong.weak_eq(&weak));
+    assert!(weak.weak_eq(&weak));
+    assert!(!strong.weak_eq(&EntityTag::strong("xyz")));
+}
+
+// Tests for evaluate()
+#[test]
+fn test_evaluate_without_conditions_proceeds() {
+    let result = evaluate(HttpMethod::Get, &Headers::new(), &validators());
+    assert_eq!(result, Precondition::Proceed);
+}
+
+#[test]
+fn test_if_none_match_matching_is_not_modified() {
+    let h = headers(&["If-None-Match: \"other\", W/\"v1\""]);
+    assert_eq!(evaluate(HttpMethod::Get, &h, &validators()), Precondition::NotModified);
+    assert_eq!(evaluate(HttpMethod::Head, &h, &validators()), Precondition::NotModified);
+}
+
+#[test]
+fn test_if_none_match_for_unsafe_method_fails() {
+    let h = headers(&["If-None-Match: *"]);
+    assert_eq!(evaluate(HttpMethod::Put, &h, &validators()), Precondition::Failed);
+}
+
+#[test]
+fn test_if_none_match_not_matching_proceeds() {
+    let h = headers(&["If-None-Match: \"other\""]);
+    assert_eq!(evaluate(HttpMethod::Get, &h, &validators()), Precondition::Proceed);
+}
+
+#[test]
+fn test_if_none_match_takes_precedence_over_if_modified_since() {
+    let h = headers(&[
+        "If-None-Match: \"other\"",
+        &format!("If-Modified-Since: {}", date(1_800_000_000)),
+    ]);
+    assert_eq!(evaluate(HttpMethod::Get, &h, &validators()), Precondition::Proceed);
+}
+
+#[test]
+fn test_if_modified_since() {
+    let same = headers(&[&format!("If-Modified-Since: {}", date(1_700_000_000))]);
+    let older = headers(&[&format!("If-Modified-Since: {}", date(1_600_000_000))]);
+
+    assert_eq!(evaluate(HttpMethod::Get, &same, &validators()), Precondition::NotModified);
+    assert_eq!(evaluate(HttpMethod::Get, &older, &validators()), Precondition::Proceed);
+}
+
+#[test]
+fn test_if_modified_since_ignored_for_unsafe_method_and_invalid_date() {
+    let h = headers(&[&format!("If-Modified-Since: {}", date(1_800_000_000))]);
+    assert_eq!(evaluate(HttpMethod::Post, &h, &validators()), Precondition::Proceed);
+
+    let invalid = headers(&["If-Modified-Since: yesterday"]);
+    assert_eq!(evaluate(HttpMethod::Get, &invalid, &validators()), Precondition::Proceed);
+}
+
+#[test]
+fn test_if_match() {
+    let matching = headers(&["If-Match: \"v1\""]);
+    let any = headers(&["If-Match: *"]);
+    let other = headers(&["If-Match: \"v2\""]);
+    let weak = headers(&["If-Match: W/\"v1\""]);
+
+    assert_eq!(evaluate(HttpMethod::Get, &matching, &validators()), Precondition::Proceed);
+    assert_eq!(evaluate(HttpMethod::Get, &any, &validators()), Precondition::Proceed);
+    assert_eq!(evaluate(HttpMethod::Get, &other, &validators()), Precondition::Failed);
+    assert_eq!(evaluate(HttpMethod::Get, &weak, &validators()), Precondition::Failed);
+}
+
+#[test]
+fn test_if_unmodified_since() {
+    let later = headers(&[&format!("If-Unmodified-Since: {}", date(1_800_000_000))]);
+    let earlier = headers(&[&format!("If-Unmodified-Since: {}", date(1_600_000_000))]);
+
+    assert_eq!(evaluate(HttpMethod::Get, &later, &validators()), Precondition::Proceed);
+    assert_eq!(evaluate(HttpMethod::Get, &earlier, &validators()), Precondition::Failed);
+}
+
+#[test]
+fn test_if_match_takes_precedence_over_if_unmodified_since() {
+    let h = headers(&[
+        "If-Match: \"v1\"",
+        &format!("If-Unmodified-Since: {}", date(1_600_000_000)),
+    ]);
+    assert_eq!(evaluate(HttpMethod::Get, &h, &validators()), Precondition::Proceed);
+}
@@ -3,6 +3,7 @@ mod tests;
 
 use crate::consts;
 use anyhow::Result;
+use std::fmt;
 
 /// A wrapper around `std::io::Read` that yields data in CRLF-terminated chunks.
 pub struct LineStream<'a, T>
@@ -32,6 +33,24 @@ where
         }
     }
 
+    /// Returns the underlying stream, e.g. to write to it directly.
+    pub const fn get_mut(&mut self) -> &mut T {
+        self.stream
+    }
+
+    /// Blocks until some data is buffered, reading from the underlying stream
+    /// only if nothing is buffered yet. Returns false if the stream ended
+    /// before any data arrived.
+    pub fn wait_for_data(&mut self) -> Result<bool> {
+        if !self.line_buffer.is_empty() || self.stream_buffer_start < self.stream_buffer_size {
+            return Ok(true);
+        }
+
+        self.stream_buffer_start = 0;
+        self.stream_buffer_size = self.stream.read(&mut self.stream_buffer)?;
+        Ok(self.stream_buffer_size > 0)
+    }
+
     /// Reads the next CRLF-terminated line from the underlying stream.
     ///
     /// This method buffers data internally and returns complete lines without
@@ -42,6 +61,14 @@ where
     /// * `Ok(Vec<u8>)` - The line content without the CRLF terminator
     /// * `Err(_)` - If an I/O error occurs while reading from the stream
     pub fn read_line(&mut self) -> Result<Vec<u8>> {
+        self.read_line_limited(usize::MAX)
+    }
+
+    /// Reads the next CRLF-terminated line like `read_line`, but fails with
+    /// `LineTooLong` as soon as the line is known to exceed `limit` bytes,
+    /// without buffering the rest of it. The stream is left in the middle of
+    /// the line, so the connection can't be used for further requests.
+    pub fn read_line_limited(&mut self, limit: usize) -> Result<Vec<u8>> {
         loop {
             // Refill buffer if exhausted
             if self.stream_buffer_start >= self.stream_buffer_size {
@@ -53,6 +80,7 @@ where
                     if self.line_buffer.is_empty() {
                         return Err(anyhow::anyhow!("EOF"));
                     }
+                    check_line_length(self.line_buffer.len(), limit)?;
                     let retval = self.line_buffer.clone();
                     self.line_buffer.clear();
                     return Ok(retval);
@@ -67,6 +95,7 @@ where
             {
                 // the CR and LR are spread across the bytes that are just read the the bytes
                 // that last read. this means the full line is the cached line bytes minus last
+                check_line_length(self.line_buffer.len() - 1, limit)?;
                 let mut retval = Vec::new();
                 retval.extend_from_slice(&self.line_buffer[..self.line_buffer.len() - 1]);
                 self.line_buffer.clear();
@@ -82,6 +111,7 @@ where
                 .position(|w| w == self.seperator)
             {
                 let end = start + pos;
+                check_line_length(self.line_buffer.len() + end - start, limit)?;
                 self.line_buffer
                     .extend_from_slice(&self.stream_buffer[start..end]);
                 self.stream_buffer_start = end + 2;
@@ -92,7 +122,12 @@ where
                 return Ok(retval);
             }
 
-            // No separator found, buffer all remaining data
+            // No separator found, buffer all remaining data. The last byte may
+            // be the CR of the separator, so it doesn't count yet.
+            check_line_length(
+                (self.line_buffer.len() + size - start).saturating_sub(1),
+                limit,
+            )?;
             self.line_buffer
                 .extend_from_slice(&self.stream_buffer[start..size]);
             self.stream_buffer_start = size;
@@ -149,6 +184,29 @@ where
     }
 }
 
+/// The error returned by `LineStream::read_line_limited` for a line longer
+/// than the limit.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LineTooLong {
+    pub limit: usize,
+}
+
+impl fmt::Display for LineTooLong {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line is longer than {} bytes", self.limit)
+    }
+}
+
+impl std::error::Error for LineTooLong {}
+
+const fn check_line_length(len: usize, limit: usize) -> Result<(), LineTooLong> {
+    if len > limit {
+        return Err(LineTooLong { limit });
+    }
+
+    Ok(())
+}
+
 fn cross_boundary_separator(head: &[u8], tail: &[u8]) -> bool {
     if head.is_empty() {
         return false;
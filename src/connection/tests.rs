@@ -294,3 +294,50 @@ fn test_consecutive_requests() {
     let body2 = line_stream.read_bytes(6).unwrap();
     assert_eq!(body2, b"World!");
 }
+
+#[test]
+fn test_read_line_limited_within_limit() {
+    let data = b"12345\r\n123456\r\n";
+    let mut stream = Cursor::new(data.to_vec());
+    let mut line_stream = LineStream::new(&mut stream);
+
+    assert_eq!(line_stream.read_line_limited(5).unwrap(), b"12345".to_vec());
+    let err = line_stream.read_line_limited(5).unwrap_err();
+    assert!(err.is::<LineTooLong>());
+}
+
+#[test]
+fn test_read_line_limited_without_separator() {
+    // the line never ends, so it must fail before the whole input is buffered
+    let data = vec![b'a'; 8192];
+    let mut stream = Cursor::new(data);
+    let mut line_stream = LineStream::new(&mut stream);
+
+    let err = line_stream.read_line_limited(100).unwrap_err();
+    assert_eq!(err.downcast_ref::<LineTooLong>().unwrap().limit, 100);
+}
+
+#[test]
+fn test_read_line_limited_split_crlf() {
+    // the CR at the end of the first read isn't part of the line
+    let chunks = vec![b"12345\r".to_vec(), b"\nnext\r\n".to_vec()];
+    let mut stream = MockReader::new(chunks);
+    let mut line_stream = LineStream::new(&mut stream);
+
+    assert_eq!(line_stream.read_line_limited(5).unwrap(), b"12345".to_vec());
+    assert_eq!(line_stream.read_line_limited(5).unwrap(), b"next".to_vec());
+}
+
+#[test]
+fn test_wait_for_data() {
+    let data = b"Line 1\r\nLi";
+    let mut stream = Cursor::new(data.to_vec());
+    let mut line_stream = LineStream::new(&mut stream);
+
+    assert!(line_stream.wait_for_data().unwrap());
+    assert_eq!(line_stream.read_line().unwrap(), b"Line 1".to_vec());
+    // the rest of the data is still buffered
+    assert!(line_stream.wait_for_data().unwrap());
+    assert_eq!(line_stream.read_line().unwrap(), b"Li".to_vec());
+    assert!(!line_stream.wait_for_data().unwrap());
+}
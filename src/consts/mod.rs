@@ -9,5 +9,29 @@ pub const HEADER_USER_AGENT: &str = "User-Agent";
 pub const HEADER_CONTENT_LENGTH: &str = "Content-Length";
 pub const HEADER_CONTENT_TYPE: &str = "Content-Type";
 pub const HEADER_CONNECTION: &str = "Connection";
+pub const HEADER_KEEP_ALIVE: &str = "Keep-Alive";
 pub const HEADER_ACCEPT_ENCODING: &str = "Accept-Encoding";
 pub const HEADER_CONTENT_ENCODING: &str = "Content-Encoding";
+pub const HEADER_VARY: &str = "Vary";
+pub const HEADER_TRANSFER_ENCODING: &str = "Transfer-Encoding";
+pub const HEADER_RANGE: &str = "Range";
+pub const HEADER_IF_RANGE: &str = "If-Range";
+pub const HEADER_ACCEPT_RANGES: &str = "Accept-Ranges";
+pub const HEADER_CONTENT_RANGE: &str = "Content-Range";
+pub const HEADER_ETAG: &str = "ETag";
+pub const HEADER_LAST_MODIFIED: &str = "Last-Modified";
+pub const HEADER_IF_MATCH: &str = "If-Match";
+pub const HEADER_IF_NONE_MATCH: &str = "If-None-Match";
+pub const HEADER_IF_MODIFIED_SINCE: &str = "If-Modified-Since";
+pub const HEADER_IF_UNMODIFIED_SINCE: &str = "If-Unmodified-Since";
+pub const HEADER_ACCEPT: &str = "Accept";
+pub const HEADER_ALLOW: &str = "Allow";
+pub const HEADER_LOCATION: &str = "Location";
+pub const HEADER_ORIGIN: &str = "Origin";
+pub const HEADER_ACCESS_CONTROL_REQUEST_METHOD: &str = "Access-Control-Request-Method";
+pub const HEADER_ACCESS_CONTROL_REQUEST_HEADERS: &str = "Access-Control-Request-Headers";
+pub const HEADER_ACCESS_CONTROL_ALLOW_ORIGIN: &str = "Access-Control-Allow-Origin";
+pub const HEADER_ACCESS_CONTROL_ALLOW_METHODS: &str = "Access-Control-Allow-Methods";
+pub const HEADER_ACCESS_CONTROL_ALLOW_HEADERS: &str = "Access-Control-Allow-Headers";
+pub const HEADER_ACCESS_CONTROL_ALLOW_CREDENTIALS: &str = "Access-Control-Allow-Credentials";
+pub const HEADER_ACCESS_CONTROL_MAX_AGE: &str = "Access-Control-Max-Age";
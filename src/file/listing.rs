@@ -0,0 +1,109 @@
+use std::fmt::Write;
+
+use super::DirEntry;
+use crate::http::uri::encode_path_segment;
+
+/// Renders a directory listing as an HTML page. `base` is the URL path of the
+/// directory and is used to build the links to its entries.
+pub fn render_html(base: &str, entries: &[DirEntry]) -> String {
+    let base = base.trim_end_matches('/');
+    let title = escape_html(&format!("Index of {base}/"));
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{title}</title></head>\n\
+         <body>\n<h1>{title}</h1>\n<table>\n\
+         <tr><th>Name</th><th>Size</th><th>Last modified</th></tr>\n"
+    );
+
+    for entry in sorted(entries) {
+        let suffix = if entry.is_dir { "/" } else { "" };
+        let size = if entry.is_dir {
+            String::from("-")
+        } else {
+            entry.metadata.len.to_string()
+        };
+        let modified = entry
+            .metadata
+            .modified
+            .map_or_else(|| String::from("-"), httpdate::fmt_http_date);
+
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"{base}/{href}{suffix}\">{name}{suffix}</a></td><td>{size}</td><td>{modified}</td></tr>",
+            href = escape_html(&encode_path_segment(&entry.name)),
+            name = escape_html(&entry.name),
+        );
+    }
+
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+/// Renders a directory listing as a JSON document.
+#[must_use]
+pub fn render_json(base: &str, entries: &[DirEntry]) -> String {
+    let items = sorted(entries)
+        .iter()
+        .map(|entry| {
+            let modified = entry.metadata.modified.map_or_else(
+                || String::from("null"),
+                |m| format!("\"{}\"", httpdate::fmt_http_date(m)),
+            );
+            let kind = if entry.is_dir { "directory" } else { "file" };
+
+            format!(
+                "{{\"name\":\"{}\",\"type\":\"{kind}\",\"size\":{},\"modified\":{modified}}}",
+                escape_json(&entry.name),
+                entry.metadata.len,
+            )
+        })
+        .collect::<Vec<String>>();
+
+    format!(
+        "{{\"path\":\"{}\",\"entries\":[{}]}}",
+        escape_json(base),
+        items.join(",")
+    )
+}
+
+/// Orders entries with directories first, then by name.
+fn sorted(entries: &[DirEntry]) -> Vec<&DirEntry> {
+    let mut sorted = entries.iter().collect::<Vec<&DirEntry>>();
+    sorted.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    sorted
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", u32::from(c));
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
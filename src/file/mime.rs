@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+pub const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+/// How many leading bytes of a file are inspected when sniffing its type.
+const SNIFF_LEN: usize = 512;
+
+const BUILTIN_TYPES: &[(&str, &str)] = &[
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("js", "text/javascript"),
+    ("mjs", "text/javascript"),
+    ("json", "application/json"),
+    ("map", "application/json"),
+    ("xml", "application/xml"),
+    ("txt", "text/plain"),
+    ("log", "text/plain"),
+    ("md", "text/markdown"),
+    ("csv", "text/csv"),
+    ("wasm", "application/wasm"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("svg", "image/svg+xml"),
+    ("ico", "image/vnd.microsoft.icon"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    ("mp3", "audio/mpeg"),
+    ("ogg", "audio/ogg"),
+    ("wav", "audio/wav"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tgz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("zst", "application/zstd"),
+    ("br", "application/x-brotli"),
+    ("7z", "application/x-7z-compressed"),
+];
+
+/// Maps files to the media type sent in the Content-Type header.
+///
+/// Types are looked up by file extension. Files with an unknown extension can
+/// optionally be sniffed by their leading bytes.
+#[derive(Debug, Clone)]
+pub struct MimeRegistry {
+    by_extension: HashMap<String, String>,
+    sniff: bool,
+}
+
+impl Default for MimeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MimeRegistry {
+    /// Creates a registry with the built-in extension map and sniffing disabled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            by_extension: BUILTIN_TYPES
+                .iter()
+                .map(|(ext, mime)| (String::from(*ext), String::from(*mime)))
+                .collect(),
+            sniff: false,
+        }
+    }
+
+    pub const fn set_sniff(&mut self, sniff: bool) {
+        self.sniff = sniff;
+    }
+
+    pub fn insert(&mut self, extension: &str, mime: &str) {
+        self.by_extension
+            .insert(extension.to_lowercase(), String::from(mime));
+    }
+
+    /// Adds the mappings of a mime.types file, overriding built-in ones.
+    ///
+    /// Each line holds a media type followed by its extensions, separated by
+    /// whitespace. Empty lines and lines starting with `#` are skipped.
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read mime map {}", path.display()))?;
+        self.load_str(&content)
+    }
+
+    pub fn load_str(&mut self, content: &str) -> Result<()> {
+        for (n, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let mime = parts.next().unwrap_or_default();
+            if !mime.contains('/') {
+                anyhow::bail!("line {}: '{mime}' is not a media type", n + 1);
+            }
+
+            for ext in parts {
+                self.insert(ext.trim_start_matches('.'), mime);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the media type registered for the extension of `path`.
+    pub fn lookup(&self, path: &str) -> Option<&str> {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let (_, ext) = name.rsplit_once('.')?;
+        self.by_extension
+            .get(&ext.to_lowercase())
+            .map(String::as_str)
+    }
+
+    /// Returns the media type of the file at `path`. If the extension is
+    /// unknown and sniffing is enabled, the leading bytes of `file` are
+    /// inspected and the file is rewound afterwards.
+    pub fn detect(&self, path: &str, file: &mut (impl Read + Seek)) -> String {
+        if let Some(mime) = self.lookup(path) {
+            return String::from(mime);
+        }
+
+        if self.sniff {
+            let mut head = Vec::with_capacity(SNIFF_LEN);
+            let read = file
+                .by_ref()
+                .take(SNIFF_LEN as u64)
+                .read_to_end(&mut head);
+            let rewound = file.seek(SeekFrom::Start(0));
+
+            if read.is_ok() && rewound.is_ok() {
+                return String::from(sniff(&head));
+            }
+        }
+
+        String::from(DEFAULT_MIME_TYPE)
+    }
+}
+
+/// Guesses the media type of content from its leading bytes.
+pub fn sniff(head: &[u8]) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\x28\xb5\x2f\xfd", "application/zstd"),
+        (b"\0asm", "application/wasm"),
+        (b"OggS", "audio/ogg"),
+        (b"ID3", "audio/mpeg"),
+        (b"\x1aE\xdf\xa3", "video/webm"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+    ];
+
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(sig, _)| head.starts_with(sig)) {
+        return mime;
+    }
+
+    if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+
+    let text = head.trim_ascii_start();
+    let starts_with = |prefix: &[u8]| {
+        text.len() >= prefix.len() && text[..prefix.len()].eq_ignore_ascii_case(prefix)
+    };
+    if starts_with(b"<!doctype html") || starts_with(b"<html") {
+        return "text/html";
+    }
+    if starts_with(b"<?xml") {
+        return "application/xml";
+    }
+
+    if looks_like_text(head) {
+        return "text/plain";
+    }
+
+    DEFAULT_MIME_TYPE
+}
+
+/// Returns true if the bytes are UTF-8 without control characters other than
+/// whitespace. A multi-byte character cut off at the end is tolerated.
+fn looks_like_text(head: &[u8]) -> bool {
+    let valid = match std::str::from_utf8(head) {
+        Ok(s) => s,
+        Err(e) if e.error_len().is_none() => {
+            // the sniffed prefix ended in the middle of a character
+            std::str::from_utf8(&head[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return false,
+    };
+
+    !valid.is_empty()
+        && !valid
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0c'))
+}
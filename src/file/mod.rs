@@ -2,15 +2,135 @@
  * This module provides components that offer file serving.
  */
 
-use std::{fmt, path::PathBuf, str::FromStr};
+mod listing;
+mod mime;
+mod resolve;
+
+#[cfg(test)]
+mod tests;
+
+pub use listing::{render_html, render_json};
+pub use mime::MimeRegistry;
+pub use resolve::{PathResolver, ResolveError};
+
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fmt, fs::File, path::PathBuf, str::FromStr};
+
+use anyhow::Context;
+
+use crate::conditional::{EntityTag, Validators};
+
+/// The content codings that can be served from precompressed files, with
+/// the extension of those files, in the order the server prefers them.
+pub const PRECOMPRESSED_EXTENSIONS: &[(&str, &str)] =
+    &[("br", "br"), ("zstd", "zst"), ("gzip", "gz")];
 
 pub trait FileRetriever {
-    fn retrieve(&self, path: &str) -> Result<Vec<u8>, FileRetrieverError>;
+    /// Opens the file for streaming. The caller reads the content from the
+    /// returned handle instead of holding the whole file in memory.
+    fn open(&self, path: &str) -> Result<FileStream, FileRetrieverError>;
+
+    /// Opens the precompressed sibling of the file at `path` for a content
+    /// coding, e.g. `app.js.br` for `br`.
+    fn open_precompressed(
+        &self,
+        path: &str,
+        coding: &str,
+    ) -> Result<FileStream, FileRetrieverError> {
+        let (_, extension) = PRECOMPRESSED_EXTENSIONS
+            .iter()
+            .find(|(c, _)| *c == coding)
+            .ok_or(FileRetrieverError::NotFound)?;
+        self.open(&format!("{path}.{extension}"))
+    }
+
+    /// Lists the entries of the directory at `path`.
+    fn list(&self, path: &str) -> Result<Vec<DirEntry>, FileRetrieverError>;
+
+    /// Reads the whole file into memory.
+    fn retrieve(&self, path: &str) -> Result<Vec<u8>, FileRetrieverError> {
+        let mut stream = self.open(path)?;
+        let mut content = Vec::new();
+        stream
+            .file
+            .read_to_end(&mut content)
+            .map_err(|e| FileRetrieverError::Other(format!("failed to read file at {path}: {e}")))?;
+        Ok(content)
+    }
+}
+
+/// An opened file along with the metadata known before reading it.
+#[derive(Debug)]
+pub struct FileStream {
+    pub file: File,
+    pub metadata: FileMetadata,
+}
+
+/// An entry of a directory listing.
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub metadata: FileMetadata,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileMetadata {
+    /// The size of the file in bytes.
+    pub len: u64,
+    /// The last modification time, if the platform reports one.
+    pub modified: Option<SystemTime>,
+}
+
+impl FileMetadata {
+    /// Returns an entity tag derived from the size and modification time, or
+    /// None if the modification time is unknown.
+    ///
+    /// A file modified within the last second may be written again without
+    /// its timestamp changing, so its tag is weak until it settles.
+    #[must_use]
+    pub fn etag(&self) -> Option<EntityTag> {
+        let modified = self.modified?;
+        let since_epoch = modified.duration_since(UNIX_EPOCH).ok()?;
+        let tag = format!(
+            "{:x}-{:x}.{:x}",
+            self.len,
+            since_epoch.as_secs(),
+            since_epoch.subsec_nanos()
+        );
+
+        let settled = SystemTime::now()
+            .duration_since(modified)
+            .is_ok_and(|age| age >= Duration::from_secs(1));
+
+        Some(if settled {
+            EntityTag::strong(&tag)
+        } else {
+            EntityTag::weak(&tag)
+        })
+    }
+
+    #[must_use]
+    pub fn validators(&self) -> Validators {
+        Validators {
+            etag: self.etag(),
+            last_modified: self.modified,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum FileRetrieverError {
     NotFound,
+    /// The path points at a directory rather than a file.
+    IsDirectory,
+    InvalidPath(String),
+    /// The path leads outside the served directory.
+    Forbidden,
     Other(String),
 }
 
@@ -18,6 +138,9 @@ impl fmt::Display for FileRetrieverError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::NotFound => write!(f, "file not found"),
+            Self::IsDirectory => write!(f, "path is a directory"),
+            Self::InvalidPath(msg) => write!(f, "invalid path: {msg}"),
+            Self::Forbidden => write!(f, "path leads outside the served directory"),
             Self::Other(msg) => write!(f, "file retriever error: {msg}"),
         }
     }
@@ -25,13 +148,43 @@ impl fmt::Display for FileRetrieverError {
 
 impl std::error::Error for FileRetrieverError {}
 
+impl From<ResolveError> for FileRetrieverError {
+    fn from(e: ResolveError) -> Self {
+        match e {
+            ResolveError::Invalid(msg) => Self::InvalidPath(msg),
+            ResolveError::Forbidden => Self::Forbidden,
+        }
+    }
+}
+
 pub trait FileSaver {
+    /// Creates the file or replaces its content.
     fn save(&self, path: &str, content: &[u8]) -> Result<(), FileSaverError>;
+
+    /// Writes `content` into an existing file starting at `offset`, or appends
+    /// it if `offset` is None. The offset may not be past the end of the file.
+    fn write_at(&self, path: &str, offset: Option<u64>, content: &[u8])
+    -> Result<(), FileSaverError>;
+
+    fn delete(&self, path: &str) -> Result<(), FileSaverError>;
 }
 
 #[derive(Debug)]
 pub enum FileSaverError {
     InvalidPath(String),
+    /// The path leads outside the served directory.
+    Forbidden,
+    NotFound,
+    /// The requested write offset is past the end of the file.
+    InvalidOffset(u64),
+    /// The parent directory doesn't exist and creating it is disabled.
+    MissingParent(String),
+    /// The parent directory couldn't be created.
+    CreateDirectory(String),
+    /// The content couldn't be written and synced to the temporary file.
+    Write(String),
+    /// The temporary file couldn't be moved over the target path.
+    Commit(String),
     Other(String),
 }
 
@@ -39,6 +192,13 @@ impl fmt::Display for FileSaverError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::InvalidPath(msg) => write!(f, "invalid path: {msg}"),
+            Self::Forbidden => write!(f, "path leads outside the served directory"),
+            Self::NotFound => write!(f, "file not found"),
+            Self::InvalidOffset(offset) => write!(f, "offset {offset} is past the end of the file"),
+            Self::MissingParent(dir) => write!(f, "parent directory {dir} does not exist"),
+            Self::CreateDirectory(msg) => write!(f, "failed to create directory: {msg}"),
+            Self::Write(msg) => write!(f, "failed to write file: {msg}"),
+            Self::Commit(msg) => write!(f, "failed to move file into place: {msg}"),
             Self::Other(msg) => write!(f, "file saver error: {msg}"),
         }
     }
@@ -46,50 +206,246 @@ impl fmt::Display for FileSaverError {
 
 impl std::error::Error for FileSaverError {}
 
+impl From<ResolveError> for FileSaverError {
+    fn from(e: ResolveError) -> Self {
+        match e {
+            ResolveError::Invalid(msg) => Self::InvalidPath(msg),
+            ResolveError::Forbidden => Self::Forbidden,
+        }
+    }
+}
+
 struct LocalFileSystem {
-    root: PathBuf,
+    resolver: PathResolver,
+    /// Create missing parent directories when saving a file.
+    create_dirs: bool,
+}
+
+/// Distinguishes the temporary files of concurrent uploads in this process.
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns true for the `.{name}.{pid}-{n}.part` files `save` writes before
+/// renaming them over the target, including ones a crash left behind.
+fn is_temp_file(name: &str) -> bool {
+    let Some(inner) = name.strip_prefix('.').and_then(|n| n.strip_suffix(".part")) else {
+        return false;
+    };
+
+    inner
+        .rsplit_once('.')
+        .and_then(|(_, id)| id.split_once('-'))
+        .is_some_and(|(pid, n)| is_number(pid) && is_number(n))
+}
+
+fn is_number(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
 }
 
 struct Dummy {}
 
 impl FileRetriever for LocalFileSystem {
-    fn retrieve(&self, path: &str) -> Result<Vec<u8>, FileRetrieverError> {
-        let mut full_path = self.root.clone();
-        full_path.push(path);
+    fn open(&self, path: &str) -> Result<FileStream, FileRetrieverError> {
+        let full_path = self.resolver.resolve(path)?;
 
         if !full_path.exists() {
             return Err(FileRetrieverError::NotFound);
         }
 
+        if full_path.is_dir() {
+            return Err(FileRetrieverError::IsDirectory);
+        }
+
         let path = full_path.as_path();
-        std::fs::read(path).map_err(|e| {
-            FileRetrieverError::Other(format!("failed to read file at {}: {e}", path.display()))
+        let file = File::open(path).map_err(|e| {
+            FileRetrieverError::Other(format!("failed to open file at {}: {e}", path.display()))
+        })?;
+        let metadata = file.metadata().map_err(|e| {
+            FileRetrieverError::Other(format!(
+                "failed to read metadata of {}: {e}",
+                path.display()
+            ))
+        })?;
+
+        if !metadata.is_file() {
+            return Err(FileRetrieverError::Other(format!(
+                "{} is not a regular file",
+                path.display()
+            )));
+        }
+
+        Ok(FileStream {
+            file,
+            metadata: FileMetadata {
+                len: metadata.len(),
+                modified: metadata.modified().ok(),
+            },
         })
     }
+
+    fn list(&self, path: &str) -> Result<Vec<DirEntry>, FileRetrieverError> {
+        let full_path = self.resolver.resolve(path)?;
+
+        if !full_path.is_dir() {
+            return Err(FileRetrieverError::NotFound);
+        }
+
+        let read_dir = std::fs::read_dir(&full_path).map_err(|e| {
+            FileRetrieverError::Other(format!(
+                "failed to read directory {}: {e}",
+                full_path.display()
+            ))
+        })?;
+
+        let mut entries = Vec::new();
+        for entry in read_dir.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // uploads in progress aren't files yet
+            if is_temp_file(&name) {
+                continue;
+            }
+
+            // follow symlinks so entries show what a request would get;
+            // dangling links are left out
+            let Ok(metadata) = std::fs::metadata(entry.path()) else {
+                continue;
+            };
+
+            entries.push(DirEntry {
+                name,
+                is_dir: metadata.is_dir(),
+                metadata: FileMetadata {
+                    len: metadata.len(),
+                    modified: metadata.modified().ok(),
+                },
+            });
+        }
+
+        Ok(entries)
+    }
 }
 
 impl FileSaver for LocalFileSystem {
+    /// Writes the content to a temporary file next to the target, syncs it to
+    /// disk and renames it over the target, so readers never see a partially
+    /// written file.
     fn save(&self, path: &str, content: &[u8]) -> Result<(), FileSaverError> {
-        if path.contains("..") {
-            return Err(FileSaverError::InvalidPath(
-                "path contains '..' which is disallowed".to_string(),
-            ));
+        let full_path = self.resolver.resolve(path)?;
+
+        let (Some(parent), Some(name)) = (full_path.parent(), full_path.file_name()) else {
+            return Err(FileSaverError::InvalidPath(String::from(
+                "path has no file name",
+            )));
+        };
+
+        if !parent.is_dir() {
+            if !self.create_dirs {
+                return Err(FileSaverError::MissingParent(parent.display().to_string()));
+            }
+
+            std::fs::create_dir_all(parent).map_err(|e| {
+                FileSaverError::CreateDirectory(format!("{}: {e}", parent.display()))
+            })?;
+        }
+
+        if full_path.is_dir() {
+            return Err(FileSaverError::InvalidPath(String::from(
+                "path is a directory",
+            )));
         }
 
-        let mut full_path = self.root.clone();
-        full_path.push(path);
+        let temp_path = parent.join(format!(
+            ".{}.{}-{}.part",
+            name.to_string_lossy(),
+            std::process::id(),
+            TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let result = write_synced(&temp_path, content)
+            .map_err(|e| FileSaverError::Write(format!("{}: {e}", temp_path.display())))
+            .and_then(|()| {
+                std::fs::rename(&temp_path, &full_path)
+                    .map_err(|e| FileSaverError::Commit(format!("{}: {e}", full_path.display())))
+            });
+
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+            return result;
+        }
 
-        std::fs::write(&full_path, content).map_err(|e| {
+        // persist the rename itself; not every file system supports syncing
+        // a directory, and the data is already safe at this point
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+
+        Ok(())
+    }
+
+    fn write_at(
+        &self,
+        path: &str,
+        offset: Option<u64>,
+        content: &[u8],
+    ) -> Result<(), FileSaverError> {
+        let full_path = self.resolver.resolve(path)?;
+
+        let other = |e: std::io::Error| {
             FileSaverError::Other(format!(
                 "failed to write file at {}: {e}",
                 full_path.display()
             ))
+        };
+
+        if full_path.is_dir() {
+            return Err(FileSaverError::InvalidPath(String::from(
+                "path is a directory",
+            )));
+        }
+
+        let mut file = match OpenOptions::new().write(true).open(&full_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Err(FileSaverError::NotFound),
+            Err(e) => return Err(other(e)),
+        };
+
+        let len = file.metadata().map_err(other)?.len();
+        let offset = offset.unwrap_or(len);
+        if offset > len {
+            return Err(FileSaverError::InvalidOffset(offset));
+        }
+
+        file.seek(SeekFrom::Start(offset)).map_err(other)?;
+        file.write_all(content).map_err(other)
+    }
+
+    fn delete(&self, path: &str) -> Result<(), FileSaverError> {
+        let full_path = self.resolver.resolve(path)?;
+
+        if full_path.is_dir() {
+            return Err(FileSaverError::InvalidPath(String::from(
+                "path is a directory",
+            )));
+        }
+
+        std::fs::remove_file(&full_path).map_err(|e| {
+            if e.kind() == ErrorKind::NotFound {
+                return FileSaverError::NotFound;
+            }
+
+            FileSaverError::Other(format!(
+                "failed to delete file at {}: {e}",
+                full_path.display()
+            ))
         })
     }
 }
 
 impl FileRetriever for Dummy {
-    fn retrieve(&self, _path: &str) -> Result<Vec<u8>, FileRetrieverError> {
+    fn open(&self, _path: &str) -> Result<FileStream, FileRetrieverError> {
+        Err(FileRetrieverError::Other(String::from("not implemented")))
+    }
+
+    fn list(&self, _path: &str) -> Result<Vec<DirEntry>, FileRetrieverError> {
         Err(FileRetrieverError::Other(String::from("not implemented")))
     }
 }
@@ -98,30 +454,55 @@ impl FileSaver for Dummy {
     fn save(&self, _path: &str, _content: &[u8]) -> Result<(), FileSaverError> {
         Err(FileSaverError::Other(String::from("not implemented")))
     }
+
+    fn write_at(
+        &self,
+        _path: &str,
+        _offset: Option<u64>,
+        _content: &[u8],
+    ) -> Result<(), FileSaverError> {
+        Err(FileSaverError::Other(String::from("not implemented")))
+    }
+
+    fn delete(&self, _path: &str) -> Result<(), FileSaverError> {
+        Err(FileSaverError::Other(String::from("not implemented")))
+    }
 }
 
 pub trait FileSystem: FileRetriever + FileSaver {}
 
 impl<T: FileRetriever + FileSaver> FileSystem for T {}
 
-pub fn create(input: Option<String>) -> anyhow::Result<Box<dyn FileSystem + Send + Sync>> {
+pub fn create(
+    input: Option<String>,
+    create_dirs: bool,
+    follow_symlinks: bool,
+) -> anyhow::Result<Box<dyn FileSystem + Send + Sync>> {
     if let Some(path) = input {
         let root = validate_path(&path)?;
-        return Ok(Box::new(LocalFileSystem { root }));
+        let resolver = PathResolver::new(&root, follow_symlinks)
+            .with_context(|| format!("failed to resolve the directory '{path}'"))?;
+        return Ok(Box::new(LocalFileSystem {
+            resolver,
+            create_dirs,
+        }));
     }
 
     Ok(Box::new(Dummy {}))
 }
 
+/// Creates the file at `path` with the content and flushes it to disk.
+fn write_synced(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
 fn validate_path(s: &str) -> anyhow::Result<PathBuf> {
     if !s.starts_with('/') {
         anyhow::bail!("The directory path is not started from root.")
     }
 
-    if s.contains("..") {
-        anyhow::bail!("The directory path contains '..' which is disallowed.")
-    }
-
     let path = PathBuf::from_str(s)?;
 
     if !path.is_absolute() {
@@ -0,0 +1,110 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Maps request paths onto files below a root directory.
+///
+/// A decoded request path is split into segments, `.` and `..` are applied
+/// lexically, and the result is checked against the canonical
+/// root so neither `..` nor a symlink can lead outside of it. Following
+/// symlinks that point outside the root can be allowed explicitly.
+#[derive(Debug, Clone)]
+pub struct PathResolver {
+    root: PathBuf,
+    follow_symlinks: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ResolveError {
+    /// The path contains a forbidden character.
+    Invalid(String),
+    /// The path leads outside the root directory.
+    Forbidden,
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(msg) => write!(f, "invalid path: {msg}"),
+            Self::Forbidden => write!(f, "path leads outside the served directory"),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+impl PathResolver {
+    /// Creates a resolver for the directory `root`, which is canonicalized so
+    /// resolved paths can be compared against it.
+    pub fn new(root: &Path, follow_symlinks: bool) -> std::io::Result<Self> {
+        Ok(Self {
+            root: root.canonicalize()?,
+            follow_symlinks,
+        })
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolves a decoded request path relative to the root.
+    ///
+    /// The path doesn't need to exist. Its existing part is canonicalized and
+    /// the missing rest is appended, so the result can be used to create a
+    /// file. An empty path resolves to the root itself.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, ResolveError> {
+        let mut segments = Vec::new();
+        if path.contains('\0') {
+            return Err(ResolveError::Invalid(String::from(
+                "path contains a NUL byte",
+            )));
+        }
+
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    if segments.pop().is_none() {
+                        return Err(ResolveError::Forbidden);
+                    }
+                }
+                _ => segments.push(segment),
+            }
+        }
+
+        // canonicalize the longest existing prefix, which resolves any
+        // symlinks in it; the segments after it don't exist yet
+        let mut existing = segments.len();
+        let base = loop {
+            if existing == 0 {
+                break self.root.clone();
+            }
+
+            let prefix = segments[..existing]
+                .iter()
+                .fold(self.root.clone(), |path, segment| path.join(segment));
+            if let Ok(canonical) = prefix.canonicalize() {
+                break canonical;
+            }
+
+            existing -= 1;
+        };
+
+        // a segment that can't be canonicalized but still exists is a
+        // dangling symlink, which would be followed when writing
+        if existing < segments.len()
+            && !self.follow_symlinks
+            && base.join(segments[existing]).symlink_metadata().is_ok()
+        {
+            return Err(ResolveError::Forbidden);
+        }
+
+        if !self.follow_symlinks && !base.starts_with(&self.root) {
+            return Err(ResolveError::Forbidden);
+        }
+
+        Ok(segments[existing..]
+            .iter()
+            .fold(base, |path, segment| path.join(segment)))
+    }
+}
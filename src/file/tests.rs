@@ -0,0 +1,396 @@
+use std::io::{Cursor, Read};
+use std::time::{Duration, UNIX_EPOCH};
+
+use super::mime::sniff;
+use super::{
+    DirEntry, FileMetadata, FileRetriever, FileRetrieverError, FileSaver, FileSaverError,
+    LocalFileSystem, MimeRegistry, PathResolver, ResolveError, render_html, render_json,
+};
+
+// MimeRegistry tests
+
+#[test]
+fn test_lookup_builtin_extension() {
+    let registry = MimeRegistry::new();
+    assert_eq!(registry.lookup("index.html"), Some("text/html"));
+    assert_eq!(registry.lookup("assets/app.js"), Some("text/javascript"));
+    assert_eq!(registry.lookup("logo.png"), Some("image/png"));
+}
+
+#[test]
+fn test_lookup_is_case_insensitive() {
+    let registry = MimeRegistry::new();
+    assert_eq!(registry.lookup("PHOTO.JPG"), Some("image/jpeg"));
+}
+
+#[test]
+fn test_lookup_uses_last_extension() {
+    let registry = MimeRegistry::new();
+    assert_eq!(registry.lookup("release.tar.gz"), Some("application/gzip"));
+}
+
+#[test]
+fn test_lookup_unknown_or_missing_extension() {
+    let registry = MimeRegistry::new();
+    assert_eq!(registry.lookup("data.unknownext"), None);
+    assert_eq!(registry.lookup("Makefile"), None);
+    // a dot in a directory name is not an extension
+    assert_eq!(registry.lookup("v1.html/readme"), None);
+}
+
+#[test]
+fn test_load_str_adds_and_overrides() {
+    let mut registry = MimeRegistry::new();
+    registry
+        .load_str("# custom types\n\ntext/x-custom  cst .cst2\napplication/javascript js\n")
+        .unwrap();
+
+    assert_eq!(registry.lookup("a.cst"), Some("text/x-custom"));
+    assert_eq!(registry.lookup("a.cst2"), Some("text/x-custom"));
+    assert_eq!(registry.lookup("a.js"), Some("application/javascript"));
+}
+
+#[test]
+fn test_load_str_rejects_invalid_media_type() {
+    let mut registry = MimeRegistry::new();
+    assert!(registry.load_str("notatype ext\n").is_err());
+}
+
+#[test]
+fn test_detect_prefers_extension() {
+    let registry = MimeRegistry::new();
+    let mut file = Cursor::new(b"\x89PNG\r\n\x1a\n".to_vec());
+    assert_eq!(registry.detect("notes.txt", &mut file), "text/plain");
+}
+
+#[test]
+fn test_detect_without_sniffing_defaults_to_octet_stream() {
+    let registry = MimeRegistry::new();
+    let mut file = Cursor::new(b"<!DOCTYPE html><html></html>".to_vec());
+    assert_eq!(registry.detect("page", &mut file), "application/octet-stream");
+}
+
+#[test]
+fn test_detect_sniffs_and_rewinds() {
+    let mut registry = MimeRegistry::new();
+    registry.set_sniff(true);
+    let mut file = Cursor::new(b"<!DOCTYPE html><html></html>".to_vec());
+
+    assert_eq!(registry.detect("page", &mut file), "text/html");
+
+    let mut content = String::new();
+    file.read_to_string(&mut content).unwrap();
+    assert_eq!(content, "<!DOCTYPE html><html></html>");
+}
+
+// sniff tests
+
+#[test]
+fn test_sniff_binary_signatures() {
+    assert_eq!(sniff(b"\x89PNG\r\n\x1a\nrest"), "image/png");
+    assert_eq!(sniff(b"\xff\xd8\xff\xe0"), "image/jpeg");
+    assert_eq!(sniff(b"GIF89a"), "image/gif");
+    assert_eq!(sniff(b"%PDF-1.7"), "application/pdf");
+    assert_eq!(sniff(b"PK\x03\x04"), "application/zip");
+    assert_eq!(sniff(b"\x1f\x8b\x08"), "application/gzip");
+    assert_eq!(sniff(b"RIFF\x00\x00\x00\x00WEBPVP8 "), "image/webp");
+}
+
+#[test]
+fn test_sniff_markup() {
+    assert_eq!(sniff(b"  \n<html><body>"), "text/html");
+    assert_eq!(sniff(b"<?xml version=\"1.0\"?>"), "application/xml");
+}
+
+#[test]
+fn test_sniff_text() {
+    assert_eq!(sniff(b"plain words\nand lines\t\r\n"), "text/plain");
+    // a multi-byte character cut off by the sniff window
+    assert_eq!(sniff(&"héllo".as_bytes()[..2]), "text/plain");
+}
+
+#[test]
+fn test_sniff_binary_fallback() {
+    assert_eq!(sniff(b"\x00\x01\x02\x03"), "application/octet-stream");
+    assert_eq!(sniff(b""), "application/octet-stream");
+}
+
+// listing tests
+
+fn entry(name: &str, is_dir: bool, len: u64) -> DirEntry {
+    DirEntry {
+        name: String::from(name),
+        is_dir,
+        metadata: FileMetadata {
+            len,
+            modified: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+        },
+    }
+}
+
+#[test]
+fn test_render_html_links_and_order() {
+    let entries = vec![entry("b.txt", false, 10), entry("sub", true, 4096), entry("a.txt", false, 3)];
+    let html = render_html("/files/docs", &entries);
+
+    assert!(html.contains("<title>Index of /files/docs/</title>"));
+    let sub = html.find("href=\"/files/docs/sub/\"").unwrap();
+    let a = html.find("href=\"/files/docs/a.txt\"").unwrap();
+    let b = html.find("href=\"/files/docs/b.txt\"").unwrap();
+    assert!(sub < a && a < b);
+    assert!(html.contains("<td>10</td><td>Tue, 14 Nov 2023 22:13:20 GMT</td>"));
+}
+
+#[test]
+fn test_render_html_escapes_names() {
+    let entries = vec![entry("<b>&\"x\" y.txt", false, 1)];
+    let html = render_html("/files/", &entries);
+
+    assert!(html.contains("href=\"/files/%3Cb%3E%26%22x%22%20y.txt\""));
+    assert!(html.contains(">&lt;b&gt;&amp;&quot;x&quot; y.txt<"));
+}
+
+#[test]
+fn test_render_json() {
+    let mut no_time = entry("new\"file", false, 5);
+    no_time.metadata.modified = None;
+    let entries = vec![no_time, entry("sub", true, 0)];
+
+    assert_eq!(
+        render_json("/files/docs", &entries),
+        "{\"path\":\"/files/docs\",\"entries\":[\
+         {\"name\":\"sub\",\"type\":\"directory\",\"size\":0,\"modified\":\"Tue, 14 Nov 2023 22:13:20 GMT\"},\
+         {\"name\":\"new\\\"file\",\"type\":\"file\",\"size\":5,\"modified\":null}]}"
+    );
+}
+
+// LocalFileSystem tests
+
+fn temp_root(name: &str) -> LocalFileSystem {
+    let root = std::env::temp_dir().join(format!("file-test-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    LocalFileSystem {
+        resolver: PathResolver::new(&root, false).unwrap(),
+        create_dirs: true,
+    }
+}
+
+#[test]
+fn test_write_at_offset_and_append() {
+    let fs = temp_root("write-at");
+    fs.save("data.bin", b"0123456789").unwrap();
+
+    fs.write_at("data.bin", Some(2), b"ab").unwrap();
+    fs.write_at("data.bin", None, b"XY").unwrap();
+    fs.write_at("data.bin", Some(12), b"!").unwrap();
+
+    assert_eq!(fs.retrieve("data.bin").unwrap(), b"01ab456789XY!");
+    std::fs::remove_dir_all(fs.resolver.root()).unwrap();
+}
+
+#[test]
+fn test_write_at_errors() {
+    let fs = temp_root("write-at-errors");
+    fs.save("data.bin", b"0123").unwrap();
+
+    assert!(matches!(
+        fs.write_at("data.bin", Some(5), b"x"),
+        Err(FileSaverError::InvalidOffset(5))
+    ));
+    assert!(matches!(
+        fs.write_at("missing.bin", None, b"x"),
+        Err(FileSaverError::NotFound)
+    ));
+    std::fs::remove_dir_all(fs.resolver.root()).unwrap();
+}
+
+#[test]
+fn test_delete() {
+    let fs = temp_root("delete");
+    fs.save("data.bin", b"0123").unwrap();
+
+    fs.delete("data.bin").unwrap();
+
+    assert!(matches!(fs.retrieve("data.bin"), Err(FileRetrieverError::NotFound)));
+    assert!(matches!(fs.delete("data.bin"), Err(FileSaverError::NotFound)));
+    std::fs::remove_dir_all(fs.resolver.root()).unwrap();
+}
+
+#[test]
+fn test_save_replaces_without_leaving_temp_files() {
+    let fs = temp_root("save-replace");
+    fs.save("data.bin", b"first").unwrap();
+    fs.save("data.bin", b"second").unwrap();
+
+    assert_eq!(fs.retrieve("data.bin").unwrap(), b"second");
+    let names = std::fs::read_dir(fs.resolver.root())
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["data.bin"]);
+    std::fs::remove_dir_all(fs.resolver.root()).unwrap();
+}
+
+#[test]
+fn test_save_creates_parent_directories() {
+    let fs = temp_root("save-nested");
+    fs.save("a/b/c.bin", b"nested").unwrap();
+
+    assert_eq!(fs.retrieve("a/b/c.bin").unwrap(), b"nested");
+    std::fs::remove_dir_all(fs.resolver.root()).unwrap();
+}
+
+#[test]
+fn test_save_without_create_dirs_reports_missing_parent() {
+    let mut fs = temp_root("save-no-create");
+    fs.create_dirs = false;
+
+    assert!(matches!(
+        fs.save("a/b/c.bin", b"nested"),
+        Err(FileSaverError::MissingParent(_))
+    ));
+    assert!(!fs.resolver.root().join("a").exists());
+    std::fs::remove_dir_all(fs.resolver.root()).unwrap();
+}
+
+#[test]
+fn test_save_onto_directory_is_invalid() {
+    let fs = temp_root("save-dir");
+    std::fs::create_dir(fs.resolver.root().join("sub")).unwrap();
+
+    assert!(matches!(
+        fs.save("sub", b"x"),
+        Err(FileSaverError::InvalidPath(_))
+    ));
+    std::fs::remove_dir_all(fs.resolver.root()).unwrap();
+}
+
+// PathResolver tests
+
+#[test]
+fn test_resolve_normalizes_segments() {
+    let fs = temp_root("resolve-normalize");
+    let root = fs.resolver.root();
+
+    assert_eq!(fs.resolver.resolve("").unwrap(), root);
+    assert_eq!(fs.resolver.resolve("a/./b//c").unwrap(), root.join("a/b/c"));
+    assert_eq!(fs.resolver.resolve("a/../b").unwrap(), root.join("b"));
+    assert_eq!(fs.resolver.resolve("/abs").unwrap(), root.join("abs"));
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_resolve_allows_dots_in_names() {
+    let fs = temp_root("resolve-dots");
+    let root = fs.resolver.root();
+
+    assert_eq!(fs.resolver.resolve("v1..2.tar").unwrap(), root.join("v1..2.tar"));
+    assert_eq!(fs.resolver.resolve("...").unwrap(), root.join("..."));
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_resolve_takes_decoded_paths() {
+    let fs = temp_root("resolve-decoded");
+    let root = fs.resolver.root();
+
+    assert_eq!(fs.resolver.resolve("a b.txt").unwrap(), root.join("a b.txt"));
+    assert_eq!(fs.resolver.resolve("\u{e9}").unwrap(), root.join("\u{e9}"));
+    // escapes were decoded with the request, so these are literal names
+    assert_eq!(fs.resolver.resolve("a%20b").unwrap(), root.join("a%20b"));
+    assert_eq!(fs.resolver.resolve("%2e%2e").unwrap(), root.join("%2e%2e"));
+    assert!(matches!(fs.resolver.resolve("a\0b"), Err(ResolveError::Invalid(_))));
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_resolve_refuses_traversal() {
+    let fs = temp_root("resolve-traversal");
+
+    for path in ["..", "../etc/passwd", "a/../../b", "a/./../../b"] {
+        assert_eq!(fs.resolver.resolve(path), Err(ResolveError::Forbidden), "{path}");
+    }
+    std::fs::remove_dir_all(fs.resolver.root()).unwrap();
+}
+
+#[test]
+fn test_resolve_symlinks() {
+    let fs = temp_root("resolve-symlinks");
+    let root = fs.resolver.root();
+    let outside = std::env::temp_dir().join(format!(
+        "file-test-{}-resolve-symlinks-outside",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&outside).unwrap();
+    std::fs::write(outside.join("secret"), b"secret").unwrap();
+    std::fs::write(root.join("inside"), b"inside").unwrap();
+
+    std::os::unix::fs::symlink(root.join("inside"), root.join("link-in")).unwrap();
+    std::os::unix::fs::symlink(&outside, root.join("link-out")).unwrap();
+    std::os::unix::fs::symlink(outside.join("missing"), root.join("dangling")).unwrap();
+
+    assert_eq!(fs.resolver.resolve("link-in").unwrap(), root.join("inside"));
+    assert_eq!(fs.resolver.resolve("link-out"), Err(ResolveError::Forbidden));
+    assert_eq!(fs.resolver.resolve("link-out/secret"), Err(ResolveError::Forbidden));
+    assert_eq!(fs.resolver.resolve("link-out/new"), Err(ResolveError::Forbidden));
+    assert_eq!(fs.resolver.resolve("dangling"), Err(ResolveError::Forbidden));
+    assert!(matches!(fs.open("link-out/secret"), Err(FileRetrieverError::Forbidden)));
+    assert!(matches!(fs.save("dangling", b"x"), Err(FileSaverError::Forbidden)));
+    assert!(!outside.join("missing").exists());
+
+    let following = PathResolver::new(root, true).unwrap();
+    assert_eq!(
+        following.resolve("link-out/secret").unwrap(),
+        outside.canonicalize().unwrap().join("secret")
+    );
+
+    std::fs::remove_dir_all(root).unwrap();
+    std::fs::remove_dir_all(&outside).unwrap();
+}
+
+#[test]
+fn test_open_precompressed() {
+    let fs = temp_root("precompressed");
+    let root = fs.resolver.root();
+    std::fs::write(root.join("app.js"), b"console.log(1)").unwrap();
+    std::fs::write(root.join("app.js.br"), b"br bytes").unwrap();
+    std::fs::write(root.join("app.js.zst"), b"zstd bytes").unwrap();
+
+    assert_eq!(fs.open_precompressed("app.js", "br").unwrap().metadata.len, 8);
+    assert_eq!(fs.open_precompressed("app.js", "zstd").unwrap().metadata.len, 10);
+    assert!(matches!(
+        fs.open_precompressed("app.js", "gzip"),
+        Err(FileRetrieverError::NotFound)
+    ));
+    assert!(matches!(
+        fs.open_precompressed("app.js", "deflate"),
+        Err(FileRetrieverError::NotFound)
+    ));
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_list_hides_upload_temp_files() {
+    let fs = temp_root("list-temp");
+    fs.save("data.txt", b"0123456789").unwrap();
+    let root = fs.resolver.resolve("").unwrap();
+    for name in [
+        ".data.txt.123-0.part",
+        ".a.b.txt.1-22.part",
+        ".hidden",
+        "notes.part",
+    ] {
+        std::fs::write(root.join(name), "x").unwrap();
+    }
+
+    let mut names = fs
+        .list("")
+        .unwrap()
+        .into_iter()
+        .map(|e| e.name)
+        .collect::<Vec<_>>();
+    names.sort();
+
+    assert_eq!(names, [".hidden", "data.txt", "notes.part"]);
+}
@@ -6,12 +6,22 @@ use std::collections::HashMap;
 use crate::consts;
 use anyhow::{Result, anyhow};
 
-#[derive(Debug, Clone)]
+/// an item of a header value that lists alternatives with quality values,
+/// such as Accept-Encoding. the quality is kept in thousandths, so `q=0.5`
+/// is stored as 500.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QualityItem {
+    pub value: String,
+    pub quality: u16,
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Headers {
     headers: HashMap<String, Vec<String>>,
 }
 
 impl Headers {
+    #[must_use]
     pub fn new() -> Self {
         Self {
             headers: HashMap::new(),
@@ -29,6 +39,7 @@ impl Headers {
     /// get returns the value of the header that matches the name. if
     /// there are multiple values, the first one is returned. returns
     /// None if the name doesn't match any headers
+    #[must_use]
     pub fn get(&self, name: &str) -> Option<&str> {
         if let Some(values) = self.headers.get(&name.to_lowercase()) {
             if values.is_empty() {
@@ -42,6 +53,14 @@ impl Headers {
         None
     }
 
+    /// `get_all` returns all values of the header that matches the name, or
+    /// an empty slice if there are none.
+    pub fn get_all(&self, name: &str) -> &[String] {
+        self.headers
+            .get(&name.to_lowercase())
+            .map_or(&[], Vec::as_slice)
+    }
+
     /// set clears all values associated with the given name and set
     /// its value to the singe value provided.
     pub fn set(&mut self, name: &str, value: &str) {
@@ -55,14 +74,15 @@ impl Headers {
             .or_insert_with(|| Vec::from([String::from(value)]));
     }
 
-    /* For future use
-
-    pub fn delete(&mut self, name: &str) {
-        let _ = self.headers.remove(name);
+    /// iter returns the name-values pairs of all headers. names are lowercased.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.headers.iter().map(|(k, v)| (k.as_str(), v.as_slice()))
     }
 
-
-    */
+    /// remove deletes all values associated with the given name.
+    pub fn remove(&mut self, name: &str) {
+        let _ = self.headers.remove(&name.to_lowercase());
+    }
 
     pub fn write(&self, stream: &mut impl std::io::Write) -> Result<()> {
         self.headers.iter().try_for_each(|(k, v)| -> Result<()> {
@@ -111,26 +131,126 @@ impl Headers {
         )
     }
 
+    /// returns true if the Content-Length header is present.
+    #[must_use]
+    pub fn has_content_length(&self) -> bool {
+        self.get(consts::HEADER_CONTENT_LENGTH).is_some()
+    }
+
+    /// returns the transfer codings listed in the Transfer-Encoding header(s),
+    /// lowercased and in the order they were applied. returns an empty vector
+    /// if the header is not present.
+    #[must_use]
+    pub fn transfer_encodings(&self) -> Vec<String> {
+        self.list_values(consts::HEADER_TRANSFER_ENCODING)
+    }
+
+    /// returns the content codings listed in the Content-Encoding header(s),
+    /// lowercased and in the order they were applied. returns an empty vector
+    /// if the header is not present.
+    #[must_use]
+    pub fn content_encodings(&self) -> Vec<String> {
+        self.list_values(consts::HEADER_CONTENT_ENCODING)
+    }
+
+    /// returns the comma-separated values of all headers with the name,
+    /// lowercased and with empty items removed.
+    fn list_values(&self, name: &str) -> Vec<String> {
+        self.get_all(name)
+            .iter()
+            .flat_map(|v| v.split(','))
+            .map(|v| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty())
+            .collect()
+    }
+
     /// returns the value of Content-Type header as &str.
     /// returns None if the header is not present.
+    #[must_use]
     pub fn content_type(&self) -> Option<&str> {
         self.get(consts::HEADER_CONTENT_TYPE)
     }
 
     /// returns the value of Connection header as &str.
     /// returns None if the header is not present.
+    #[must_use]
     pub fn connection(&self) -> Option<&str> {
         self.get(consts::HEADER_CONNECTION)
     }
 
+    /// returns the value of Accept header as &str.
+    /// returns None if the header is not present.
+    #[must_use]
+    pub fn accept(&self) -> Option<&str> {
+        self.get(consts::HEADER_ACCEPT)
+    }
+
+    /// returns the value of Range header as &str.
+    /// returns None if the header is not present.
+    #[must_use]
+    pub fn range(&self) -> Option<&str> {
+        self.get(consts::HEADER_RANGE)
+    }
+
+    /// returns the value of If-Range header as &str.
+    /// returns None if the header is not present.
+    #[must_use]
+    pub fn if_range(&self) -> Option<&str> {
+        self.get(consts::HEADER_IF_RANGE)
+    }
+
     /// returns the value of Accept-Encoding header as Option<Vec<String>>.
     /// returns None if the header is not present.
+    #[must_use]
     pub fn accept_encodings(&self) -> Option<Vec<String>> {
         self.headers
             .get(&consts::HEADER_ACCEPT_ENCODING.to_lowercase())
             .cloned()
     }
 
+    /// returns the codings listed in the Accept-Encoding header with their
+    /// quality values. returns None if the header is not present.
+    #[must_use]
+    pub fn accept_encoding_qualities(&self) -> Option<Vec<QualityItem>> {
+        self.accept_encodings()
+            .map(|values| parse_quality_list(&values.join(",")))
+    }
+
+    /// returns the content coding that should be applied to a response.
+    /// `supported` lists the codings the server can produce in its order of
+    /// preference. the coding with the highest quality wins and ties go to
+    /// the server's preference.
+    ///
+    /// returns None if the response should be sent unencoded: the header is
+    /// missing, no supported coding is acceptable, or identity is explicitly
+    /// preferred over all of them.
+    #[must_use]
+    pub fn preferred_encoding<'a>(&self, supported: &[&'a str]) -> Option<&'a str> {
+        let items = self.accept_encoding_qualities()?;
+        let quality_of = |coding: &str| {
+            items
+                .iter()
+                .find(|item| item.value == coding)
+                .or_else(|| items.iter().find(|item| item.value == "*"))
+                .map(|item| item.quality)
+        };
+
+        let mut best: Option<(&'a str, u16)> = None;
+        for coding in supported {
+            let quality = quality_of(coding).unwrap_or(0);
+            if quality > 0 && best.is_none_or(|(_, q)| quality > q) {
+                best = Some((coding, quality));
+            }
+        }
+
+        let (coding, quality) = best?;
+        if quality_of("identity").is_some_and(|identity| identity > quality) {
+            return None;
+        }
+
+        Some(coding)
+    }
+
     /// sets the Accept-Encoding header. the value is expected to be a
     /// comma-separated string of encodings.
     pub fn set_accept_encoding(&mut self, value: &str) {
@@ -148,6 +268,51 @@ impl Headers {
     }
 }
 
+/// parses a comma-separated list of values with optional quality values,
+/// e.g. `gzip;q=0.8, br`. values are lowercased and a missing q defaults to
+/// 1. items with a malformed q are skipped.
+#[must_use]
+pub fn parse_quality_list(value: &str) -> Vec<QualityItem> {
+    value
+        .split(',')
+        .filter_map(|item| {
+            let mut params = item.split(';');
+            let value = params.next()?.trim().to_lowercase();
+            if value.is_empty() {
+                return None;
+            }
+
+            let mut quality = 1000;
+            for param in params {
+                let Some((name, q)) = param.split_once('=') else {
+                    continue;
+                };
+                if name.trim().eq_ignore_ascii_case("q") {
+                    quality = parse_qvalue(q.trim())?;
+                }
+            }
+
+            Some(QualityItem { value, quality })
+        })
+        .collect()
+}
+
+/// parses a qvalue, which is a number between 0 and 1 with at most three
+/// decimals, into thousandths.
+fn parse_qvalue(s: &str) -> Option<u16> {
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    if frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let frac = format!("{frac:0<3}").parse::<u16>().ok()?;
+    match int {
+        "0" => Some(frac),
+        "1" if frac == 0 => Some(1000),
+        _ => None,
+    }
+}
+
 fn wire_format(name: &String, values: &[String]) -> Vec<u8> {
     let mut result: Vec<u8> = Vec::new();
     result.extend_from_slice(name.as_bytes());
@@ -1,4 +1,4 @@
-use super::Headers;
+use super::{Headers, QualityItem, parse_quality_list};
 
 #[test]
 fn test_new_creates_empty_headers() {
@@ -664,3 +664,117 @@ fn test_set_content_length_write_format() {
     let output = String::from_utf8(buffer).unwrap();
     assert_eq!(output, "content-length: 42\r\n");
 }
+
+// Tests for transfer_encodings() method
+#[test]
+fn test_transfer_encodings_empty_when_absent() {
+    let headers = Headers::new();
+    assert!(headers.transfer_encodings().is_empty());
+}
+
+#[test]
+fn test_transfer_encodings_split_and_lowercased() {
+    let mut headers = Headers::new();
+    headers.read(b"Transfer-Encoding: GZIP, Chunked").unwrap();
+    assert_eq!(headers.transfer_encodings(), vec!["gzip", "chunked"]);
+}
+
+#[test]
+fn test_transfer_encodings_from_multiple_headers() {
+    let mut headers = Headers::new();
+    headers.read(b"Transfer-Encoding: gzip").unwrap();
+    headers.read(b"Transfer-Encoding: chunked").unwrap();
+    assert_eq!(headers.transfer_encodings(), vec!["gzip", "chunked"]);
+}
+
+// Tests for quality values and content coding negotiation
+fn item(value: &str, quality: u16) -> QualityItem {
+    QualityItem {
+        value: value.to_string(),
+        quality,
+    }
+}
+
+#[test]
+fn test_parse_quality_list() {
+    assert_eq!(
+        parse_quality_list("gzip;q=0.8, BR, identity; q=0, *;q=0.001"),
+        vec![
+            item("gzip", 800),
+            item("br", 1000),
+            item("identity", 0),
+            item("*", 1)
+        ]
+    );
+}
+
+#[test]
+fn test_parse_quality_list_skips_malformed_qvalues() {
+    assert_eq!(
+        parse_quality_list("a;q=1.5, b;q=0.1234, c;q=x, d;q=1.000, e;q=0., , f;level=1"),
+        vec![item("d", 1000), item("e", 0), item("f", 1000)]
+    );
+}
+
+#[test]
+fn test_accept_encoding_qualities_from_read() {
+    let mut headers = Headers::new();
+    headers.read(b"Accept-Encoding: gzip;q=0.5, br").unwrap();
+
+    assert_eq!(
+        headers.accept_encoding_qualities().unwrap(),
+        vec![item("gzip", 500), item("br", 1000)]
+    );
+}
+
+fn preferred(accept_encoding: Option<&str>) -> Option<&'static str> {
+    let mut headers = Headers::new();
+    if let Some(value) = accept_encoding {
+        headers
+            .read(format!("Accept-Encoding: {value}").as_bytes())
+            .unwrap();
+    }
+
+    headers.preferred_encoding(&["br", "zstd", "gzip", "deflate"])
+}
+
+#[test]
+fn test_preferred_encoding_follows_server_order_on_ties() {
+    assert_eq!(preferred(Some("gzip, deflate, br")), Some("br"));
+    assert_eq!(preferred(Some("deflate, gzip")), Some("gzip"));
+    assert_eq!(preferred(Some("*")), Some("br"));
+}
+
+#[test]
+fn test_preferred_encoding_honors_quality() {
+    assert_eq!(preferred(Some("br;q=0.5, gzip")), Some("gzip"));
+    assert_eq!(preferred(Some("br;q=0, gzip;q=0.1")), Some("gzip"));
+    assert_eq!(preferred(Some("*;q=0.5, zstd")), Some("zstd"));
+    assert_eq!(preferred(Some("*, br;q=0, zstd;q=0")), Some("gzip"));
+}
+
+#[test]
+fn test_preferred_encoding_none_when_nothing_acceptable() {
+    assert_eq!(preferred(None), None);
+    assert_eq!(preferred(Some("")), None);
+    assert_eq!(preferred(Some("compress, exi")), None);
+    assert_eq!(preferred(Some("gzip;q=0")), None);
+    assert_eq!(preferred(Some("*;q=0")), None);
+}
+
+#[test]
+fn test_preferred_encoding_respects_preferred_identity() {
+    assert_eq!(preferred(Some("gzip;q=0.5, identity")), None);
+    assert_eq!(preferred(Some("gzip, identity;q=0.5")), Some("gzip"));
+    assert_eq!(preferred(Some("gzip, identity")), Some("gzip"));
+}
+
+#[test]
+fn test_content_encodings() {
+    let mut headers = Headers::new();
+    assert!(headers.content_encodings().is_empty());
+
+    headers.read(b"Content-Encoding: GZIP, br").unwrap();
+    headers.read(b"Content-Encoding: identity").unwrap();
+    assert_eq!(headers.content_encodings(), vec!["gzip", "br", "identity"]);
+}
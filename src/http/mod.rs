@@ -3,6 +3,7 @@ basic building components such as method and status code are defined in this pac
 */
 pub mod method;
 pub mod status;
+pub mod uri;
 
 #[cfg(test)]
 mod tests;
@@ -1,23 +1,174 @@
 use crate::consts::{CRLF, SPACE};
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use std::fmt;
 
 const HTTP_1_1: &[u8] = b"HTTP/1.1";
 
-#[derive(Debug)]
-#[allow(dead_code)]
-pub enum HttpStatus {
-    Ok = 200,                  // 200
-    Created = 201,             // 201
-    NoContent = 204,           // 204
-    BadRequest = 400,          // 400
-    Unauthorized = 401,        // 401
-    Forbidden = 403,           // 403
-    NotFound = 404,            // 404
-    MethodNotAllowed = 405,    // 405
-    InternalServerError = 500, // 500
+/// Defines `HttpStatus` with a variant for each registered status code and
+/// the lookups between variants, codes and reason phrases.
+macro_rules! statuses {
+    ($($variant:ident = $code:literal, $reason:literal;)+) => {
+        /// The status of a response: one of the codes registered with IANA,
+        /// or any other three-digit code with a reason phrase of its own.
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub enum HttpStatus {
+            $(
+                #[doc = concat!(stringify!($code), " ", $reason)]
+                $variant,
+            )+
+            /// A code with a custom reason phrase, created with
+            /// `HttpStatus::custom`.
+            Custom(CustomStatus),
+        }
+
+        impl HttpStatus {
+            /// Returns the numeric status code.
+            pub const fn code(&self) -> u16 {
+                match self {
+                    $(Self::$variant => $code,)+
+                    Self::Custom(custom) => custom.code,
+                }
+            }
+
+            /// Returns the reason phrase sent in the status line.
+            pub fn reason(&self) -> &str {
+                match self {
+                    $(Self::$variant => $reason,)+
+                    Self::Custom(custom) => &custom.reason,
+                }
+            }
+
+            /// Returns the variant of a registered code.
+            const fn registered(code: u16) -> Option<Self> {
+                match code {
+                    $($code => Some(Self::$variant),)+
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+statuses! {
+    Continue = 100, "Continue";
+    SwitchingProtocols = 101, "Switching Protocols";
+    Processing = 102, "Processing";
+    EarlyHints = 103, "Early Hints";
+    Ok = 200, "OK";
+    Created = 201, "Created";
+    Accepted = 202, "Accepted";
+    NonAuthoritativeInformation = 203, "Non-Authoritative Information";
+    NoContent = 204, "No Content";
+    ResetContent = 205, "Reset Content";
+    PartialContent = 206, "Partial Content";
+    MultiStatus = 207, "Multi-Status";
+    AlreadyReported = 208, "Already Reported";
+    ImUsed = 226, "IM Used";
+    MultipleChoices = 300, "Multiple Choices";
+    MovedPermanently = 301, "Moved Permanently";
+    Found = 302, "Found";
+    SeeOther = 303, "See Other";
+    NotModified = 304, "Not Modified";
+    UseProxy = 305, "Use Proxy";
+    TemporaryRedirect = 307, "Temporary Redirect";
+    PermanentRedirect = 308, "Permanent Redirect";
+    BadRequest = 400, "Bad Request";
+    Unauthorized = 401, "Unauthorized";
+    PaymentRequired = 402, "Payment Required";
+    Forbidden = 403, "Forbidden";
+    NotFound = 404, "Not Found";
+    MethodNotAllowed = 405, "Method Not Allowed";
+    NotAcceptable = 406, "Not Acceptable";
+    ProxyAuthenticationRequired = 407, "Proxy Authentication Required";
+    RequestTimeout = 408, "Request Timeout";
+    Conflict = 409, "Conflict";
+    Gone = 410, "Gone";
+    LengthRequired = 411, "Length Required";
+    PreconditionFailed = 412, "Precondition Failed";
+    ContentTooLarge = 413, "Content Too Large";
+    UriTooLong = 414, "URI Too Long";
+    UnsupportedMediaType = 415, "Unsupported Media Type";
+    RangeNotSatisfiable = 416, "Range Not Satisfiable";
+    ExpectationFailed = 417, "Expectation Failed";
+    MisdirectedRequest = 421, "Misdirected Request";
+    UnprocessableContent = 422, "Unprocessable Content";
+    Locked = 423, "Locked";
+    FailedDependency = 424, "Failed Dependency";
+    TooEarly = 425, "Too Early";
+    UpgradeRequired = 426, "Upgrade Required";
+    PreconditionRequired = 428, "Precondition Required";
+    TooManyRequests = 429, "Too Many Requests";
+    RequestHeaderFieldsTooLarge = 431, "Request Header Fields Too Large";
+    UnavailableForLegalReasons = 451, "Unavailable For Legal Reasons";
+    InternalServerError = 500, "Internal Server Error";
+    NotImplemented = 501, "Not Implemented";
+    BadGateway = 502, "Bad Gateway";
+    ServiceUnavailable = 503, "Service Unavailable";
+    GatewayTimeout = 504, "Gateway Timeout";
+    HttpVersionNotSupported = 505, "HTTP Version Not Supported";
+    VariantAlsoNegotiates = 506, "Variant Also Negotiates";
+    InsufficientStorage = 507, "Insufficient Storage";
+    LoopDetected = 508, "Loop Detected";
+    NotExtended = 510, "Not Extended";
+    NetworkAuthenticationRequired = 511, "Network Authentication Required";
+}
+
+/// A status code with a custom reason phrase. Both are validated when it is
+/// created, so it always makes a well-formed status line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomStatus {
+    code: u16,
+    reason: String,
 }
 
 impl HttpStatus {
+    /// Creates a status with any three-digit code and reason phrase, e.g.
+    /// `HttpStatus::custom(299, "Cached")`. A registered code can be given a
+    /// different phrase this way too.
+    pub fn custom(code: u16, reason: &str) -> Result<Self> {
+        validate_code(code)?;
+        if reason.chars().any(|c| c.is_control() && c != '\t') {
+            return Err(anyhow!(
+                "reason phrase {reason:?} contains a control character"
+            ));
+        }
+
+        Ok(Self::Custom(CustomStatus {
+            code,
+            reason: String::from(reason),
+        }))
+    }
+
+    /// Returns true for 1xx codes.
+    #[must_use]
+    pub const fn is_informational(&self) -> bool {
+        matches!(self.code(), 100..=199)
+    }
+
+    /// Returns true for 2xx codes.
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        matches!(self.code(), 200..=299)
+    }
+
+    /// Returns true for 3xx codes.
+    #[must_use]
+    pub const fn is_redirection(&self) -> bool {
+        matches!(self.code(), 300..=399)
+    }
+
+    /// Returns true for 4xx codes.
+    #[must_use]
+    pub const fn is_client_error(&self) -> bool {
+        matches!(self.code(), 400..=499)
+    }
+
+    /// Returns true for 5xx codes.
+    #[must_use]
+    pub const fn is_server_error(&self) -> bool {
+        matches!(self.code(), 500..=599)
+    }
+
     pub fn write_status_line(&self, stream: &mut impl std::io::Write) -> Result<()> {
         // A status line of HTTP/1.1 looks like this
         // HTTP/1.1 200 OK
@@ -25,39 +176,48 @@ impl HttpStatus {
 
         stream.write_all(HTTP_1_1)?; // default to HTTP 1.1 protocol
         stream.write_all(SPACE)?;
-        stream.write_all(self.status_code().as_bytes())?;
+        stream.write_all(self.code().to_string().as_bytes())?;
         stream.write_all(SPACE)?;
-        stream.write_all(self.status_phrase().as_bytes())?;
+        stream.write_all(self.reason().as_bytes())?;
         stream.write_all(CRLF)?;
 
         Ok(())
     }
+}
 
-    const fn status_phrase(&self) -> &'static str {
-        match self {
-            Self::Ok => "OK",
-            Self::Created => "Created",
-            Self::NoContent => "No Content",
-            Self::BadRequest => "Bad Request",
-            Self::Unauthorized => "Unauthorized",
-            Self::Forbidden => "Forbidden",
-            Self::NotFound => "Not Found",
-            Self::MethodNotAllowed => "Method Not Allowed",
-            Self::InternalServerError => "Internal Server Error",
-        }
+impl TryFrom<u16> for HttpStatus {
+    type Error = anyhow::Error;
+
+    /// Returns the variant of a registered code. Other three-digit codes
+    /// become a custom status without a reason phrase.
+    fn try_from(code: u16) -> Result<Self> {
+        validate_code(code)?;
+        Ok(Self::registered(code).unwrap_or_else(|| {
+            Self::Custom(CustomStatus {
+                code,
+                reason: String::new(),
+            })
+        }))
     }
+}
 
-    const fn status_code(&self) -> &'static str {
-        match self {
-            Self::Ok => "200",
-            Self::Created => "201",
-            Self::NoContent => "204",
-            Self::BadRequest => "400",
-            Self::Unauthorized => "401",
-            Self::Forbidden => "403",
-            Self::NotFound => "404",
-            Self::MethodNotAllowed => "405",
-            Self::InternalServerError => "500",
-        }
+impl From<HttpStatus> for u16 {
+    fn from(status: HttpStatus) -> Self {
+        status.code()
+    }
+}
+
+impl fmt::Display for HttpStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.code(), self.reason())
+    }
+}
+
+/// A status line has room for exactly three digits.
+fn validate_code(code: u16) -> Result<()> {
+    if (100..=999).contains(&code) {
+        Ok(())
+    } else {
+        Err(anyhow!("status code {code} doesn't have three digits"))
     }
 }
@@ -1,5 +1,6 @@
 use super::method::HttpMethod;
 use super::status::HttpStatus;
+use super::uri;
 
 #[test]
 fn test_parse_get() {
@@ -174,13 +175,223 @@ fn test_status_no_content_write_status_line() {
     assert_eq!(buffer, b"HTTP/1.1 204 No Content\r\n");
 }
 
+#[test]
+fn test_status_partial_content_write_status_line() {
+    let mut buffer = Vec::new();
+    HttpStatus::PartialContent
+        .write_status_line(&mut buffer)
+        .unwrap();
+    assert_eq!(buffer, b"HTTP/1.1 206 Partial Content\r\n");
+}
+
+#[test]
+fn test_status_range_not_satisfiable_write_status_line() {
+    let mut buffer = Vec::new();
+    HttpStatus::RangeNotSatisfiable
+        .write_status_line(&mut buffer)
+        .unwrap();
+    assert_eq!(buffer, b"HTTP/1.1 416 Range Not Satisfiable\r\n");
+}
+
+#[test]
+fn test_status_not_modified_write_status_line() {
+    let mut buffer = Vec::new();
+    HttpStatus::NotModified
+        .write_status_line(&mut buffer)
+        .unwrap();
+    assert_eq!(buffer, b"HTTP/1.1 304 Not Modified\r\n");
+}
+
+#[test]
+fn test_status_precondition_failed_write_status_line() {
+    let mut buffer = Vec::new();
+    HttpStatus::PreconditionFailed
+        .write_status_line(&mut buffer)
+        .unwrap();
+    assert_eq!(buffer, b"HTTP/1.1 412 Precondition Failed\r\n");
+}
+
+#[test]
+fn test_status_conflict_write_status_line() {
+    let mut buffer = Vec::new();
+    HttpStatus::Conflict.write_status_line(&mut buffer).unwrap();
+    assert_eq!(buffer, b"HTTP/1.1 409 Conflict\r\n");
+}
+
+#[test]
+fn test_status_unsupported_media_type_write_status_line() {
+    let mut buffer = Vec::new();
+    HttpStatus::UnsupportedMediaType
+        .write_status_line(&mut buffer)
+        .unwrap();
+    assert_eq!(buffer, b"HTTP/1.1 415 Unsupported Media Type\r\n");
+}
+
 #[test]
 fn test_status_enum_values() {
-    assert_eq!(HttpStatus::Ok as u16, 200);
-    assert_eq!(HttpStatus::NoContent as u16, 204);
-    assert_eq!(HttpStatus::BadRequest as u16, 400);
-    assert_eq!(HttpStatus::Unauthorized as u16, 401);
-    assert_eq!(HttpStatus::Forbidden as u16, 403);
-    assert_eq!(HttpStatus::NotFound as u16, 404);
-    assert_eq!(HttpStatus::InternalServerError as u16, 500);
+    assert_eq!(HttpStatus::Ok.code(), 200);
+    assert_eq!(HttpStatus::NoContent.code(), 204);
+    assert_eq!(HttpStatus::PartialContent.code(), 206);
+    assert_eq!(HttpStatus::NotModified.code(), 304);
+    assert_eq!(HttpStatus::BadRequest.code(), 400);
+    assert_eq!(HttpStatus::Unauthorized.code(), 401);
+    assert_eq!(HttpStatus::Forbidden.code(), 403);
+    assert_eq!(HttpStatus::NotFound.code(), 404);
+    assert_eq!(HttpStatus::Conflict.code(), 409);
+    assert_eq!(HttpStatus::PreconditionFailed.code(), 412);
+    assert_eq!(HttpStatus::ContentTooLarge.code(), 413);
+    assert_eq!(HttpStatus::UnsupportedMediaType.code(), 415);
+    assert_eq!(HttpStatus::RangeNotSatisfiable.code(), 416);
+    assert_eq!(HttpStatus::InternalServerError.code(), 500);
+}
+
+#[test]
+fn test_status_registered_codes() {
+    let cases = [
+        (HttpStatus::MovedPermanently, 301, "Moved Permanently"),
+        (HttpStatus::Found, 302, "Found"),
+        (HttpStatus::TemporaryRedirect, 307, "Temporary Redirect"),
+        (HttpStatus::PermanentRedirect, 308, "Permanent Redirect"),
+        (HttpStatus::LengthRequired, 411, "Length Required"),
+        (HttpStatus::UriTooLong, 414, "URI Too Long"),
+        (HttpStatus::TooManyRequests, 429, "Too Many Requests"),
+        (
+            HttpStatus::RequestHeaderFieldsTooLarge,
+            431,
+            "Request Header Fields Too Large",
+        ),
+        (HttpStatus::NotImplemented, 501, "Not Implemented"),
+        (HttpStatus::ServiceUnavailable, 503, "Service Unavailable"),
+        (
+            HttpStatus::HttpVersionNotSupported,
+            505,
+            "HTTP Version Not Supported",
+        ),
+    ];
+
+    for (status, code, reason) in cases {
+        assert_eq!(status.code(), code);
+        assert_eq!(status.reason(), reason);
+        assert_eq!(HttpStatus::try_from(code).unwrap(), status);
+    }
+}
+
+#[test]
+fn test_status_try_from_unregistered_code() {
+    let status = HttpStatus::try_from(299).unwrap();
+    assert_eq!(status.code(), 299);
+    assert_eq!(status.reason(), "");
+
+    let mut buffer = Vec::new();
+    status.write_status_line(&mut buffer).unwrap();
+    assert_eq!(buffer, b"HTTP/1.1 299 \r\n");
+
+    assert!(HttpStatus::try_from(99).is_err());
+    assert!(HttpStatus::try_from(1000).is_err());
+}
+
+#[test]
+fn test_status_custom_reason() {
+    let status = HttpStatus::custom(404, "Nothing Here").unwrap();
+    assert_eq!(status.code(), 404);
+    assert_ne!(status, HttpStatus::NotFound);
+    assert!(status.is_client_error());
+
+    let mut buffer = Vec::new();
+    status.write_status_line(&mut buffer).unwrap();
+    assert_eq!(buffer, b"HTTP/1.1 404 Nothing Here\r\n");
+
+    assert_eq!(
+        HttpStatus::custom(599, "Odd").unwrap().to_string(),
+        "599 Odd"
+    );
+    assert!(HttpStatus::custom(42, "Too Short").is_err());
+    assert!(HttpStatus::custom(200, "OK\r\nX-Injected: 1").is_err());
+}
+
+#[test]
+fn test_status_classification() {
+    assert!(HttpStatus::Continue.is_informational());
+    assert!(HttpStatus::NoContent.is_success());
+    assert!(HttpStatus::NotModified.is_redirection());
+    assert!(HttpStatus::TooManyRequests.is_client_error());
+    assert!(HttpStatus::BadGateway.is_server_error());
+
+    assert!(!HttpStatus::Ok.is_redirection());
+    assert!(!HttpStatus::NotFound.is_server_error());
+    assert!(!HttpStatus::InternalServerError.is_client_error());
+}
+
+#[test]
+fn test_status_display_and_into_u16() {
+    assert_eq!(HttpStatus::NotFound.to_string(), "404 Not Found");
+    assert_eq!(u16::from(HttpStatus::Created), 201);
+}
+
+// URI tests
+
+#[test]
+fn test_split_target() {
+    assert_eq!(uri::split_target("/a/b"), ("/a/b", None));
+    assert_eq!(uri::split_target("/a?x=1&y"), ("/a", Some("x=1&y")));
+    assert_eq!(uri::split_target("/a?"), ("/a", Some("")));
+    assert_eq!(uri::split_target("/a?b?c"), ("/a", Some("b?c")));
+}
+
+#[test]
+fn test_decode_path() {
+    assert_eq!(
+        uri::decode_path("/files/my%20file.txt").unwrap(),
+        "/files/my file.txt"
+    );
+    assert_eq!(uri::decode_path("/%C3%A9").unwrap(), "/\u{e9}");
+    assert_eq!(uri::decode_path("/a+b").unwrap(), "/a+b");
+    assert_eq!(uri::decode_path("/%252F").unwrap(), "/%2F");
+    assert_eq!(uri::decode_path("/%2e%2e/x").unwrap(), "/../x");
+    assert_eq!(uri::decode_path("/a%2Fb").unwrap(), "/a/b");
+}
+
+#[test]
+fn test_decode_path_rejects_malformed_escapes() {
+    for path in ["/a%zz", "/a%4", "/%ff", "/a%00"] {
+        assert!(uri::decode_path(path).is_err(), "{path}");
+    }
+}
+
+#[test]
+fn test_has_encoded_slash() {
+    assert!(uri::has_encoded_slash("/a%2Fb"));
+    assert!(uri::has_encoded_slash("/a%2fb"));
+    assert!(!uri::has_encoded_slash("/a/b"));
+    assert!(!uri::has_encoded_slash("/%252F"));
+    assert!(!uri::has_encoded_slash("/%2"));
+}
+
+#[test]
+fn test_parse_query() {
+    let pairs = |query: &str| uri::parse_query(query);
+    let owned = |items: &[(&str, &str)]| {
+        items
+            .iter()
+            .map(|(n, v)| (String::from(*n), String::from(*v)))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        pairs("a=1&b=2&a=3"),
+        owned(&[("a", "1"), ("b", "2"), ("a", "3")])
+    );
+    assert_eq!(pairs("q=hello+world%21"), owned(&[("q", "hello world!")]));
+    assert_eq!(pairs("flag&&x="), owned(&[("flag", ""), ("x", "")]));
+    assert_eq!(pairs("a=1=2"), owned(&[("a", "1=2")]));
+    assert_eq!(pairs("bad=%zz%4"), owned(&[("bad", "%zz%4")]));
+    assert_eq!(pairs("k%20ey=%E2%9C%93"), owned(&[("k ey", "\u{2713}")]));
+    assert!(pairs("").is_empty());
+}
+
+#[test]
+fn test_encode_path() {
+    assert_eq!(uri::encode_path_segment("a b/c"), "a%20b%2Fc");
+    assert_eq!(uri::encode_path("dir/my file#1"), "dir/my%20file%231");
+    assert_eq!(uri::encode_path("a-b_c.d~e"), "a-b_c.d~e");
 }
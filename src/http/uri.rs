@@ -0,0 +1,111 @@
+use std::fmt::Write;
+
+/// Splits a request-target into its path and its query, without the `?`.
+#[must_use]
+pub fn split_target(target: &str) -> (&str, Option<&str>) {
+    target
+        .split_once('?')
+        .map_or((target, None), |(path, query)| (path, Some(query)))
+}
+
+/// Decodes the `%XX` escapes of a request path.
+///
+/// Fails if an escape is malformed, or the result isn't UTF-8 or contains a
+/// NUL. An encoded `/` is decoded like any other character, so a path that
+/// is split into segments should be split before it's decoded.
+pub fn decode_path(path: &str) -> Result<String, String> {
+    let bytes = percent_decode(path, false).ok_or_else(|| format!("bad escape in '{path}'"))?;
+    let decoded =
+        String::from_utf8(bytes).map_err(|_e| format!("'{path}' doesn't decode to UTF-8"))?;
+
+    if decoded.contains('\0') {
+        return Err(String::from("path contains a NUL byte"));
+    }
+
+    Ok(decoded)
+}
+
+/// Returns true if a still percent-encoded path contains an encoded `/`.
+#[must_use]
+pub fn has_encoded_slash(path: &str) -> bool {
+    // a '%' can only start an escape, so "%2F" can't be part of another one
+    path.as_bytes()
+        .windows(3)
+        .any(|w| w[0] == b'%' && w[1] == b'2' && w[2].eq_ignore_ascii_case(&b'f'))
+}
+
+/// Parses an `application/x-www-form-urlencoded` query into its name and
+/// value pairs in order. A `+` stands for a space, malformed escapes are
+/// kept as they are and a name without `=` has an empty value.
+#[must_use]
+pub fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode_query_component(name), decode_query_component(value))
+        })
+        .collect()
+}
+
+fn decode_query_component(s: &str) -> String {
+    let bytes = percent_decode(s, true).unwrap_or_default();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Decodes `%XX` escapes. If `lenient`, malformed escapes are kept and `+`
+/// is decoded as a space, otherwise a malformed escape returns None.
+fn percent_decode(s: &str, lenient: bool) -> Option<Vec<u8>> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(b) => {
+                        decoded.push(b);
+                        i += 3;
+                        continue;
+                    }
+                    None if lenient => decoded.push(b'%'),
+                    None => return None,
+                }
+            }
+            b'+' if lenient => decoded.push(b' '),
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+
+    Some(decoded)
+}
+
+/// Percent-encodes everything but unreserved characters, so a file name can
+/// be used as a single path segment of a URL.
+#[must_use]
+pub fn encode_path_segment(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(char::from(b));
+        } else {
+            let _ = write!(encoded, "%{b:02X}");
+        }
+    }
+    encoded
+}
+
+/// Percent-encodes each segment of a decoded path, keeping the slashes.
+pub fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(encode_path_segment)
+        .collect::<Vec<String>>()
+        .join("/")
+}
@@ -0,0 +1,20 @@
+/*
+ * An HTTP/1.1 server that can be embedded in other programs. Applications
+ * mount their own handlers next to the optional built-in ones through
+ * `server::HttpServer::builder`.
+ */
+
+pub mod body;
+pub mod compression;
+pub mod conditional;
+pub mod connection;
+pub mod consts;
+pub mod file;
+pub mod header;
+pub mod http;
+pub mod middleware;
+pub mod range;
+pub mod request;
+pub mod response;
+pub mod router;
+pub mod server;
@@ -1,44 +1,233 @@
-mod body;
-mod connection;
-mod consts;
-mod file;
-mod header;
-mod http;
-mod request;
-mod response;
-mod router;
-mod server;
-
 #[allow(unused_imports)]
 use anyhow::Result;
 use clap::Parser;
-use std::net::TcpListener;
+use codecrafters_http_server::http::method::HttpMethod;
+use codecrafters_http_server::{compression, file, middleware, request, router, server};
+use std::path::PathBuf;
+use std::time::Duration;
 
 fn main() -> Result<()> {
     let arg = Args::parse();
-    let file_server = file::create(arg.directory)?;
-    let router = router::Router::new(file_server);
-    let server = server::HttpServer::new(router);
-
-    let listener = TcpListener::bind("127.0.0.1:4221").unwrap();
-    println!("Server started at 127.0.0.1:4221");
-
-    for stream in listener.incoming() {
-        match stream {
-            Ok(stream) => {
-                server.handle(stream);
-            }
-            Err(e) => {
-                println!("error: {e}");
-            }
-        }
+    let compression = compression_policy(&arg);
+    let cors = cors_policy(&arg);
+    let file_server = file::create(arg.directory, !arg.no_create_dirs, arg.follow_symlinks)?;
+    let mut mime_types = file::MimeRegistry::new();
+    mime_types.set_sniff(arg.sniff_mime);
+    if let Some(path) = &arg.mime_types {
+        mime_types.load(path)?;
+    }
+    let options = router::FileOptions {
+        mime_types,
+        autoindex: arg.autoindex,
+        index_file: arg.index_file,
+        compression: compression.clone(),
+    };
+    let config = server::ServerConfig {
+        bind: arg.bind,
+        port: arg.port,
+        workers: arg.workers,
+        drain_timeout: Duration::from_secs(arg.drain_timeout),
+        limits: request::RequestLimits {
+            max_request_line: arg.max_request_line,
+            max_headers: arg.max_headers,
+            max_header_bytes: arg.max_header_bytes,
+            max_body: arg.max_body,
+        },
+        keep_alive_timeout: Duration::from_secs(arg.keep_alive_timeout),
+        header_timeout: Duration::from_secs(arg.header_timeout),
+        body_timeout: Duration::from_secs(arg.body_timeout),
+        min_body_rate: arg.min_body_rate,
+    };
+    let mut builder = server::HttpServer::builder()
+        .config(config)?
+        .middleware(middleware::ConnectionManager);
+    if let Some(cors) = cors {
+        builder = builder.middleware(middleware::Cors::new(cors));
     }
+    let server = builder
+        .middleware(middleware::BodyDecoding::new(arg.max_decoded_body))
+        .middleware(middleware::Compression::new(compression))
+        .index()?
+        .echo()?
+        .user_agent()?
+        .files(file_server, options)?
+        .build();
+
+    let shutdown = server.shutdown_handle();
+    ctrlc::set_handler(move || {
+        println!("Received shutdown signal");
+        shutdown.shutdown();
+    })?;
+
+    server.run()?;
+    println!("Server stopped");
 
     Ok(())
 }
 
+fn compression_policy(arg: &Args) -> compression::CompressionPolicy {
+    let mut policy = compression::CompressionPolicy {
+        enabled: !arg.no_compression,
+        min_size: arg.compression_min_size,
+        level: arg.compression_level,
+        excluded_routes: arg.no_compression_route.clone(),
+        ..Default::default()
+    };
+    if let Some(types) = &arg.compression_types {
+        policy.content_types.clone_from(types);
+    }
+
+    policy
+}
+
+/// Returns the CORS policy, or None if no origin is allowed.
+fn cors_policy(arg: &Args) -> Option<middleware::CorsPolicy> {
+    if arg.cors_origin.is_empty() {
+        return None;
+    }
+
+    let mut policy = middleware::CorsPolicy {
+        allowed_origins: arg.cors_origin.clone(),
+        allowed_headers: arg.cors_headers.clone(),
+        allow_credentials: arg.cors_credentials,
+        max_age: arg.cors_max_age,
+        ..Default::default()
+    };
+    if let Some(methods) = &arg.cors_methods {
+        policy.allowed_methods.clone_from(methods);
+    }
+
+    Some(policy)
+}
+
 #[derive(Parser, Debug)]
+#[allow(clippy::struct_excessive_bools)]
 struct Args {
     #[arg(long)]
     directory: Option<String>,
+
+    /// Fail uploads into directories that don't exist instead of creating them.
+    #[arg(long)]
+    no_create_dirs: bool,
+
+    /// Follow symlinks that point outside the directory.
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// A mime.types file with extra extension to media type mappings.
+    #[arg(long)]
+    mime_types: Option<PathBuf>,
+
+    /// Guess the media type of files with unknown extensions from their content.
+    #[arg(long)]
+    sniff_mime: bool,
+
+    /// List the content of directories requested under /files.
+    #[arg(long)]
+    autoindex: bool,
+
+    /// Serve this file, e.g. index.html, for directories that contain it.
+    #[arg(long)]
+    index_file: Option<String>,
+
+    /// The address the server listens on.
+    #[arg(long, default_value = "127.0.0.1")]
+    bind: String,
+
+    /// The port the server listens on. Use 0 to let the OS pick a free port.
+    #[arg(long, default_value_t = 4221)]
+    port: u16,
+
+    /// The number of worker threads handling connections.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    workers: u16,
+
+    /// Seconds to wait for in-flight connections to finish after a shutdown signal.
+    #[arg(long, default_value_t = 30)]
+    drain_timeout: u64,
+
+    /// Seconds a keep-alive connection may wait for its next request.
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    keep_alive_timeout: u64,
+
+    /// Seconds a client has to send the request line and headers; slower ones
+    /// are answered with 408.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    header_timeout: u64,
+
+    /// Seconds a client has to send the request body; slower ones are
+    /// answered with 408.
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+    body_timeout: u64,
+
+    /// Every this many bytes of request body received extend the body timeout
+    /// by a second. Use 0 for a fixed body timeout.
+    #[arg(long, default_value_t = 500)]
+    min_body_rate: u64,
+
+    /// The longest request line in bytes; longer ones are answered with 414.
+    #[arg(long, default_value_t = request::DEFAULT_MAX_REQUEST_LINE)]
+    max_request_line: usize,
+
+    /// The most header fields a request may have; more are answered with 431.
+    #[arg(long, default_value_t = request::DEFAULT_MAX_HEADERS)]
+    max_headers: usize,
+
+    /// The most bytes of header fields a request may have; more are answered
+    /// with 431.
+    #[arg(long, default_value_t = request::DEFAULT_MAX_HEADER_BYTES)]
+    max_header_bytes: usize,
+
+    /// The largest request body in bytes as sent; larger ones are answered
+    /// with 413.
+    #[arg(long, default_value_t = request::DEFAULT_MAX_BODY)]
+    max_body: usize,
+
+    /// Never compress response bodies.
+    #[arg(long)]
+    no_compression: bool,
+
+    /// The smallest response body in bytes that gets compressed.
+    #[arg(long, default_value_t = 256)]
+    compression_min_size: usize,
+
+    /// How hard to compress response bodies.
+    #[arg(long, value_enum, default_value_t = compression::CompressionLevel::Default)]
+    compression_level: compression::CompressionLevel,
+
+    /// Comma-separated media types to compress, replacing the built-in list.
+    /// A `type/*` entry matches any subtype.
+    #[arg(long, value_delimiter = ',')]
+    compression_types: Option<Vec<String>>,
+
+    /// Never compress responses to paths under this route. Can be repeated.
+    #[arg(long)]
+    no_compression_route: Vec<String>,
+
+    /// The largest request body in bytes accepted after decompressing it.
+    #[arg(long, default_value_t = middleware::DEFAULT_MAX_DECODED_BODY)]
+    max_decoded_body: usize,
+
+    /// Allow cross-origin requests from this origin, or `*` for any. Can be
+    /// repeated. CORS headers are only sent if at least one origin is given.
+    #[arg(long)]
+    cors_origin: Vec<String>,
+
+    /// Comma-separated methods cross-origin requests may use, replacing the
+    /// default GET, HEAD and POST.
+    #[arg(long, value_delimiter = ',')]
+    cors_methods: Option<Vec<HttpMethod>>,
+
+    /// Comma-separated request headers cross-origin requests may send, or `*`
+    /// for any.
+    #[arg(long, value_delimiter = ',')]
+    cors_headers: Vec<String>,
+
+    /// Allow cross-origin requests to carry credentials such as cookies.
+    #[arg(long)]
+    cors_credentials: bool,
+
+    /// Seconds browsers may cache the answer to a preflight request.
+    #[arg(long)]
+    cors_max_age: Option<u64>,
 }
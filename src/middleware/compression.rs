@@ -0,0 +1,31 @@
+use anyhow::Result;
+
+use super::Middleware;
+use crate::compression::CompressionPolicy;
+use crate::request::Request;
+use crate::response::Response;
+use crate::router::State;
+
+/// Compresses response bodies with the best content coding the client
+/// accepts, for the responses the policy allows.
+#[derive(Debug, Clone, Default)]
+pub struct Compression {
+    policy: CompressionPolicy,
+}
+
+impl Compression {
+    #[must_use]
+    pub const fn new(policy: CompressionPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl Middleware for Compression {
+    fn after(&self, req: &Request, resp: &mut Response, _state: &State) -> Result<()> {
+        if self.policy.allows(req.path(), resp) {
+            resp.negotiate_encoding(req.headers(), self.policy.level)?;
+        }
+
+        Ok(())
+    }
+}
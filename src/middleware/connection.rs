@@ -0,0 +1,31 @@
+use anyhow::Result;
+
+use super::Middleware;
+use crate::consts;
+use crate::request::Request;
+use crate::response::Response;
+use crate::router::State;
+use crate::server::ShutdownHandle;
+
+/// Marks the response with `Connection: close` while the server is shutting
+/// down, so the client knows not to send another request.
+///
+/// The server closes the connection after sending such a response. A
+/// client's own `Connection: close` is honored by the server itself, as HTTP
+/// requires.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectionManager;
+
+impl Middleware for ConnectionManager {
+    fn after(&self, _req: &Request, resp: &mut Response, state: &State) -> Result<()> {
+        let shutting_down = state
+            .get::<ShutdownHandle>()
+            .is_some_and(ShutdownHandle::is_shutdown);
+
+        if shutting_down {
+            resp.set_header(consts::HEADER_CONNECTION, "close");
+        }
+
+        Ok(())
+    }
+}
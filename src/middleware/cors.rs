@@ -0,0 +1,158 @@
+use anyhow::Result;
+
+use super::Middleware;
+use crate::consts;
+use crate::http::method::HttpMethod;
+use crate::http::status::HttpStatus;
+use crate::request::Request;
+use crate::response::Response;
+use crate::router::State;
+
+/// Which cross-origin requests browsers may make to the server.
+#[derive(Debug, Clone)]
+pub struct CorsPolicy {
+    /// Origins such as `https://dashboard.example.com`, or `*` for any.
+    pub allowed_origins: Vec<String>,
+    /// The methods a preflight request may ask for.
+    pub allowed_methods: Vec<HttpMethod>,
+    /// The request headers a preflight request may ask for, or `*` for any.
+    pub allowed_headers: Vec<String>,
+    /// Whether requests may carry cookies and other credentials.
+    pub allow_credentials: bool,
+    /// How long in seconds a browser may cache the result of a preflight.
+    pub max_age: Option<u64>,
+}
+
+impl Default for CorsPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: vec![HttpMethod::Get, HttpMethod::Head, HttpMethod::Post],
+            allowed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age: None,
+        }
+    }
+}
+
+impl CorsPolicy {
+    fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|o| o == "*")
+    }
+
+    fn allows_origin(&self, origin: &str) -> bool {
+        self.allows_any_origin()
+            || self
+                .allowed_origins
+                .iter()
+                .any(|o| o.eq_ignore_ascii_case(origin))
+    }
+}
+
+/// Adds `Access-Control-*` headers to the responses to requests from allowed
+/// origins and answers their preflight requests directly.
+///
+/// Requests from other origins are left alone, so the browser refuses to
+/// hand the response to the page.
+#[derive(Debug, Clone, Default)]
+pub struct Cors {
+    policy: CorsPolicy,
+}
+
+impl Cors {
+    #[must_use]
+    pub const fn new(policy: CorsPolicy) -> Self {
+        Self { policy }
+    }
+
+    /// Sets the headers every response to an allowed origin gets.
+    fn allow_origin(&self, resp: &mut Response, origin: &str) {
+        // credentials can't be combined with the `*` wildcard
+        if self.policy.allows_any_origin() && !self.policy.allow_credentials {
+            resp.set_header(consts::HEADER_ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+        } else {
+            resp.set_header(consts::HEADER_ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        }
+
+        if self.policy.allow_credentials {
+            resp.set_header(consts::HEADER_ACCESS_CONTROL_ALLOW_CREDENTIALS, "true");
+        }
+    }
+
+    fn preflight_response(&self, req: &Request, origin: &str) -> Response {
+        let mut resp = Response::new(HttpStatus::NoContent);
+        self.allow_origin(&mut resp, origin);
+
+        let methods = self
+            .policy
+            .allowed_methods
+            .iter()
+            .map(|method| method.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ");
+        resp.set_header(consts::HEADER_ACCESS_CONTROL_ALLOW_METHODS, &methods);
+
+        let requested = req
+            .headers()
+            .get(consts::HEADER_ACCESS_CONTROL_REQUEST_HEADERS);
+        let headers = if self.policy.allowed_headers.iter().any(|h| h == "*") {
+            // echo the request, as `*` isn't honored with credentials
+            requested.map(String::from)
+        } else if self.policy.allowed_headers.is_empty() {
+            None
+        } else {
+            Some(self.policy.allowed_headers.join(", "))
+        };
+        if let Some(headers) = headers {
+            resp.set_header(consts::HEADER_ACCESS_CONTROL_ALLOW_HEADERS, &headers);
+        }
+
+        if let Some(max_age) = self.policy.max_age {
+            resp.set_header(consts::HEADER_ACCESS_CONTROL_MAX_AGE, &max_age.to_string());
+        }
+
+        resp
+    }
+}
+
+impl Middleware for Cors {
+    fn before(&self, req: &mut Request, _state: &State) -> Result<Option<Response>> {
+        let Some(origin) = req.headers().get(consts::HEADER_ORIGIN) else {
+            return Ok(None);
+        };
+
+        // a preflight asks whether the actual request may be sent
+        let is_preflight = *req.method() == HttpMethod::Options
+            && req
+                .headers()
+                .get(consts::HEADER_ACCESS_CONTROL_REQUEST_METHOD)
+                .is_some();
+        if !is_preflight || !self.policy.allows_origin(origin) {
+            return Ok(None);
+        }
+
+        let mut resp = self.preflight_response(req, origin);
+        add_vary(&self.policy, &mut resp);
+        Ok(Some(resp))
+    }
+
+    fn after(&self, req: &Request, resp: &mut Response, _state: &State) -> Result<()> {
+        add_vary(&self.policy, resp);
+
+        if let Some(origin) = req.headers().get(consts::HEADER_ORIGIN)
+            && self.policy.allows_origin(origin)
+        {
+            self.allow_origin(resp, origin);
+        }
+
+        Ok(())
+    }
+}
+
+/// Caches must keep the responses to different origins apart, unless every
+/// origin gets the same `*`.
+fn add_vary(policy: &CorsPolicy, resp: &mut Response) {
+    if !policy.allows_any_origin() || policy.allow_credentials {
+        resp.add_vary(consts::HEADER_ORIGIN);
+    }
+}
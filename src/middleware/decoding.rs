@@ -0,0 +1,56 @@
+use anyhow::Result;
+
+use super::Middleware;
+use crate::consts;
+use crate::http::status::HttpStatus;
+use crate::request::{ContentDecodingError, Request};
+use crate::response::{self, Response};
+use crate::router::State;
+
+/// The default for the largest request body accepted after decoding it.
+pub const DEFAULT_MAX_DECODED_BODY: usize = 64 * 1024 * 1024;
+
+/// Undoes the Content-Encoding of request bodies before routing, so handlers
+/// always see the plain body. Bodies that can't be decoded are answered with
+/// 415, 413 or 400.
+#[derive(Debug, Clone, Copy)]
+pub struct BodyDecoding {
+    /// The largest body in bytes accepted after decoding.
+    limit: usize,
+}
+
+impl BodyDecoding {
+    #[must_use]
+    pub const fn new(limit: usize) -> Self {
+        Self { limit }
+    }
+}
+
+impl Default for BodyDecoding {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_DECODED_BODY)
+    }
+}
+
+impl Middleware for BodyDecoding {
+    fn before(&self, req: &mut Request, _state: &State) -> Result<Option<Response>> {
+        Ok(req
+            .decode_body(self.limit)
+            .err()
+            .map(|e| decoding_error_response(&e)))
+    }
+}
+
+/// Builds the response to a request whose body couldn't be decoded.
+fn decoding_error_response(e: &ContentDecodingError) -> Response {
+    match e {
+        ContentDecodingError::Unsupported(_) => {
+            let mut resp = Response::new(HttpStatus::UnsupportedMediaType);
+            // tell the client which codings it can use instead
+            resp.set_header(consts::HEADER_ACCEPT_ENCODING, "gzip, deflate, br, zstd");
+            resp
+        }
+        ContentDecodingError::TooLarge(_) => Response::new(HttpStatus::ContentTooLarge),
+        ContentDecodingError::Corrupt(msg) => response::bad_request(msg),
+    }
+}
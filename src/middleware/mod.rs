@@ -0,0 +1,97 @@
+/*
+ * This module defines middlewares, which run around the router for every
+ * request, and the built-in ones for request body decoding, response
+ * compression, CORS and connection management.
+ */
+
+mod compression;
+mod connection;
+mod cors;
+mod decoding;
+
+#[cfg(test)]
+mod tests;
+
+pub use compression::Compression;
+pub use connection::ConnectionManager;
+pub use cors::{Cors, CorsPolicy};
+pub use decoding::{BodyDecoding, DEFAULT_MAX_DECODED_BODY};
+
+use anyhow::Result;
+
+use crate::request::Request;
+use crate::response::Response;
+use crate::router::{Router, State};
+
+/// Behavior applied to every request regardless of the route it takes.
+///
+/// `before` runs ahead of routing and may modify the request or answer it
+/// directly, in which case neither the router nor the remaining middlewares
+/// see the request. `after` may modify the response on its way out.
+pub trait Middleware: Send + Sync {
+    /// Returns a response to skip routing, or None to continue.
+    fn before(&self, _req: &mut Request, _state: &State) -> Result<Option<Response>> {
+        Ok(None)
+    }
+
+    fn after(&self, _req: &Request, _resp: &mut Response, _state: &State) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// An ordered list of middlewares wrapped around the router.
+///
+/// The `before` hooks run in order and the `after` hooks in reverse order, so
+/// the first middleware sees the request first and the response last. A
+/// middleware that answers a request in `before` has its response passed
+/// only through the `after` hooks of the middlewares ahead of it.
+#[derive(Default)]
+pub struct Chain {
+    middlewares: Vec<Box<dyn Middleware>>,
+}
+
+impl Chain {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a middleware, which then runs inside the ones added before.
+    pub fn push<M: Middleware + 'static>(&mut self, middleware: M) {
+        self.middlewares.push(Box::new(middleware));
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.middlewares.is_empty()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.middlewares.len()
+    }
+
+    /// Runs the request through the middlewares and the router.
+    pub fn handle(&self, router: &Router, req: &mut Request, state: &State) -> Result<Response> {
+        let mut entered = 0;
+        let mut answered = None;
+        for middleware in &self.middlewares {
+            answered = middleware.before(req, state)?;
+            if answered.is_some() {
+                break;
+            }
+            entered += 1;
+        }
+
+        let mut resp = match answered {
+            Some(resp) => resp,
+            None => router.handle(req, state)?,
+        };
+
+        for middleware in self.middlewares[..entered].iter().rev() {
+            middleware.after(req, &mut resp, state)?;
+        }
+
+        Ok(resp)
+    }
+}
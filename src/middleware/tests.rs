@@ -0,0 +1,291 @@
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+use super::{BodyDecoding, Chain, Compression, ConnectionManager, Cors, CorsPolicy, Middleware};
+use crate::compression::CompressionPolicy;
+use crate::http::method::HttpMethod;
+use crate::http::status::HttpStatus;
+use crate::request::{Request, from_reader};
+use crate::response::{self, Response};
+use crate::router::{Router, State};
+use crate::server::ShutdownHandle;
+
+fn request(raw: &str) -> Request {
+    from_reader(&mut Cursor::new(raw.as_bytes().to_vec())).unwrap()
+}
+
+fn get(path: &str, headers: &str) -> Request {
+    request(&format!("GET {path} HTTP/1.1\r\n{headers}\r\n"))
+}
+
+fn output(resp: Response) -> String {
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    String::from_utf8_lossy(&buffer).into_owned()
+}
+
+fn router() -> Router {
+    Router::new()
+        .route(
+            HttpMethod::Get,
+            "/text",
+            |_req: &Request, _state: &State| {
+                let mut resp = response::ok();
+                resp.set_str_body(&"hello ".repeat(100));
+                Ok(resp)
+            },
+        )
+        .unwrap()
+}
+
+/// Records the order its hooks run in and optionally answers requests.
+struct Recorder {
+    name: &'static str,
+    log: Arc<Mutex<Vec<String>>>,
+    answer: bool,
+}
+
+impl Middleware for Recorder {
+    fn before(&self, _req: &mut Request, _state: &State) -> Result<Option<Response>> {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("before {}", self.name));
+        Ok(self.answer.then(|| Response::new(HttpStatus::Forbidden)))
+    }
+
+    fn after(&self, _req: &Request, resp: &mut Response, _state: &State) -> Result<()> {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("after {}", self.name));
+        resp.set_header("x-seen-by", self.name);
+        Ok(())
+    }
+}
+
+fn recorders(answering: Option<&'static str>) -> (Chain, Arc<Mutex<Vec<String>>>) {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut chain = Chain::new();
+    for name in ["a", "b", "c"] {
+        chain.push(Recorder {
+            name,
+            log: Arc::clone(&log),
+            answer: answering == Some(name),
+        });
+    }
+    (chain, log)
+}
+
+#[test]
+fn test_chain_runs_after_hooks_in_reverse_order() {
+    let (chain, log) = recorders(None);
+
+    let resp = chain
+        .handle(&router(), &mut get("/text", ""), &State::new())
+        .unwrap();
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            "before a", "before b", "before c", "after c", "after b", "after a"
+        ]
+    );
+    let out = output(resp);
+    assert!(out.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(out.contains("x-seen-by: a\r\n"));
+}
+
+#[test]
+fn test_chain_short_circuits_before_routing() {
+    let (chain, log) = recorders(Some("b"));
+
+    let resp = chain
+        .handle(&router(), &mut get("/text", ""), &State::new())
+        .unwrap();
+
+    assert_eq!(*log.lock().unwrap(), ["before a", "before b", "after a"]);
+    assert!(output(resp).starts_with("HTTP/1.1 403 Forbidden\r\n"));
+}
+
+#[test]
+fn test_empty_chain_only_routes() {
+    let chain = Chain::new();
+    assert!(chain.is_empty());
+
+    let resp = chain
+        .handle(&router(), &mut get("/none", ""), &State::new())
+        .unwrap();
+    assert!(output(resp).starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
+
+#[test]
+fn test_connection_manager_closes_on_shutdown() {
+    let mut chain = Chain::new();
+    chain.push(ConnectionManager);
+    let shutdown = ShutdownHandle::default();
+    let mut state = State::new();
+    state.insert(shutdown.clone());
+
+    let resp = chain
+        .handle(&router(), &mut get("/text", ""), &state)
+        .unwrap();
+    assert_eq!(resp.header("connection"), Some("keep-alive"));
+
+    shutdown.shutdown();
+    let resp = chain
+        .handle(&router(), &mut get("/text", ""), &state)
+        .unwrap();
+    assert_eq!(resp.header("connection"), Some("close"));
+}
+
+#[test]
+fn test_body_decoding_answers_unsupported_codings() {
+    let mut chain = Chain::new();
+    chain.push(BodyDecoding::default());
+
+    let mut req = request(
+        "POST /text HTTP/1.1\r\nContent-Encoding: compress\r\nContent-Length: 3\r\n\r\nabc",
+    );
+    let out = output(chain.handle(&router(), &mut req, &State::new()).unwrap());
+    assert!(out.starts_with("HTTP/1.1 415 Unsupported Media Type\r\n"));
+    assert!(out.contains("accept-encoding: gzip, deflate, br, zstd\r\n"));
+}
+
+#[test]
+fn test_compression_follows_policy() {
+    let mut chain = Chain::new();
+    chain.push(Compression::new(CompressionPolicy::default()));
+
+    let mut req = get("/text", "Accept-Encoding: gzip\r\n");
+    let resp = chain.handle(&router(), &mut req, &State::new()).unwrap();
+    assert_eq!(resp.header("content-encoding"), Some("gzip"));
+
+    let mut chain = Chain::new();
+    chain.push(Compression::new(CompressionPolicy {
+        enabled: false,
+        ..Default::default()
+    }));
+    let resp = chain.handle(&router(), &mut req, &State::new()).unwrap();
+    assert_eq!(resp.header("content-encoding"), None);
+}
+
+fn cors_chain(policy: CorsPolicy) -> Chain {
+    let mut chain = Chain::new();
+    chain.push(Cors::new(policy));
+    chain
+}
+
+fn dashboard_policy() -> CorsPolicy {
+    CorsPolicy {
+        allowed_origins: vec![String::from("https://dash.example.com")],
+        allowed_methods: vec![HttpMethod::Get, HttpMethod::Put],
+        allowed_headers: vec![String::from("Content-Type"), String::from("X-Token")],
+        allow_credentials: true,
+        max_age: Some(600),
+    }
+}
+
+#[test]
+fn test_cors_answers_preflight() {
+    let chain = cors_chain(dashboard_policy());
+
+    let mut req = request(
+        "OPTIONS /text HTTP/1.1\r\nOrigin: https://dash.example.com\r\n\
+         Access-Control-Request-Method: PUT\r\n\
+         Access-Control-Request-Headers: x-token\r\n\r\n",
+    );
+    let resp = chain.handle(&router(), &mut req, &State::new()).unwrap();
+
+    assert_eq!(
+        resp.header("access-control-allow-origin"),
+        Some("https://dash.example.com")
+    );
+    assert_eq!(
+        resp.header("access-control-allow-methods"),
+        Some("GET, PUT")
+    );
+    assert_eq!(
+        resp.header("access-control-allow-headers"),
+        Some("Content-Type, X-Token")
+    );
+    assert_eq!(
+        resp.header("access-control-allow-credentials"),
+        Some("true")
+    );
+    assert_eq!(resp.header("access-control-max-age"), Some("600"));
+    assert_eq!(resp.header("vary"), Some("Origin"));
+    assert!(output(resp).starts_with("HTTP/1.1 204 No Content\r\n"));
+}
+
+#[test]
+fn test_cors_ignores_preflight_from_other_origins() {
+    let chain = cors_chain(dashboard_policy());
+
+    let mut req = request(
+        "OPTIONS /text HTTP/1.1\r\nOrigin: https://evil.example.com\r\n\
+         Access-Control-Request-Method: PUT\r\n\r\n",
+    );
+    let resp = chain.handle(&router(), &mut req, &State::new()).unwrap();
+
+    assert_eq!(resp.header("access-control-allow-origin"), None);
+    let out = output(resp);
+    // the router answers the OPTIONS request itself
+    assert!(out.starts_with("HTTP/1.1 204 No Content\r\n"));
+    assert!(out.contains("allow: GET, HEAD, OPTIONS\r\n"));
+}
+
+#[test]
+fn test_cors_decorates_actual_requests() {
+    let chain = cors_chain(dashboard_policy());
+
+    let mut req = get("/text", "Origin: https://dash.example.com\r\n");
+    let resp = chain.handle(&router(), &mut req, &State::new()).unwrap();
+    assert_eq!(
+        resp.header("access-control-allow-origin"),
+        Some("https://dash.example.com")
+    );
+    assert_eq!(
+        resp.header("access-control-allow-credentials"),
+        Some("true")
+    );
+    assert_eq!(resp.header("access-control-allow-methods"), None);
+
+    let resp = chain
+        .handle(&router(), &mut get("/text", ""), &State::new())
+        .unwrap();
+    assert_eq!(resp.header("access-control-allow-origin"), None);
+    assert_eq!(resp.header("vary"), Some("Origin"));
+}
+
+#[test]
+fn test_cors_any_origin() {
+    let chain = cors_chain(CorsPolicy {
+        allowed_origins: vec![String::from("*")],
+        allowed_headers: vec![String::from("*")],
+        ..Default::default()
+    });
+
+    let mut req = get("/text", "Origin: https://a.example.com\r\n");
+    let resp = chain.handle(&router(), &mut req, &State::new()).unwrap();
+    assert_eq!(resp.header("access-control-allow-origin"), Some("*"));
+    assert_eq!(resp.header("vary"), None);
+
+    let mut req = request(
+        "OPTIONS /text HTTP/1.1\r\nOrigin: https://a.example.com\r\n\
+         Access-Control-Request-Method: POST\r\n\
+         Access-Control-Request-Headers: x-a, x-b\r\n\r\n",
+    );
+    let resp = chain.handle(&router(), &mut req, &State::new()).unwrap();
+    assert_eq!(
+        resp.header("access-control-allow-methods"),
+        Some("GET, HEAD, POST")
+    );
+    assert_eq!(
+        resp.header("access-control-allow-headers"),
+        Some("x-a, x-b")
+    );
+    assert_eq!(resp.header("access-control-max-age"), None);
+}
@@ -0,0 +1,289 @@
+/*
+ * This module implements byte range requests: parsing the Range header and
+ * producing the body of a multipart/byteranges response.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use httpdate::HttpDate;
+
+use crate::conditional::{EntityTag, Validators};
+
+/// Requests with more ranges than this are served in full, so a client can't
+/// make the server seek around a file thousands of times for one response.
+const MAX_RANGES: usize = 16;
+
+/// An inclusive range of byte positions within a representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    #[must_use]
+    pub const fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// A range always includes its first byte, so it is only empty if its
+    /// end comes before its start.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.end < self.start
+    }
+
+    /// Returns the value of the Content-Range header for this range.
+    #[must_use]
+    pub fn content_range(&self, total: u64) -> String {
+        format!("bytes {}-{}/{total}", self.start, self.end)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RangeError {
+    /// The header is malformed or uses a unit other than bytes. Such a
+    /// header is ignored and the full representation is served.
+    Invalid,
+    /// None of the ranges overlap the representation.
+    Unsatisfiable,
+}
+
+/// Returns the value of the Content-Range header of a 416 response.
+#[must_use]
+pub fn unsatisfied_content_range(total: u64) -> String {
+    format!("bytes */{total}")
+}
+
+/// Parses the value of a Range header against a representation of `total`
+/// bytes, returning the satisfiable ranges in the order they were requested.
+///
+/// Ranges that extend past the end are truncated and ranges that start past
+/// the end are dropped.
+pub fn parse(value: &str, total: u64) -> Result<Vec<ByteRange>, RangeError> {
+    let (unit, specs) = value.split_once('=').ok_or(RangeError::Invalid)?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return Err(RangeError::Invalid);
+    }
+
+    let specs = specs
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>();
+    if specs.is_empty() || specs.len() > MAX_RANGES {
+        return Err(RangeError::Invalid);
+    }
+
+    let mut ranges = Vec::with_capacity(specs.len());
+    for spec in specs {
+        if let Some(range) = parse_spec(spec, total)? {
+            ranges.push(range);
+        }
+    }
+
+    if ranges.is_empty() {
+        return Err(RangeError::Unsatisfiable);
+    }
+
+    Ok(ranges)
+}
+
+/// Parses a single range spec. Returns None if it is valid but can't be
+/// satisfied.
+fn parse_spec(spec: &str, total: u64) -> Result<Option<ByteRange>, RangeError> {
+    let (first, last) = spec.split_once('-').ok_or(RangeError::Invalid)?;
+    let (first, last) = (first.trim(), last.trim());
+
+    if first.is_empty() {
+        // suffix range: the last N bytes
+        let suffix = parse_position(last)?;
+        if suffix == 0 || total == 0 {
+            return Ok(None);
+        }
+
+        return Ok(Some(ByteRange {
+            start: total.saturating_sub(suffix),
+            end: total - 1,
+        }));
+    }
+
+    let start = parse_position(first)?;
+    let end = if last.is_empty() {
+        u64::MAX
+    } else {
+        parse_position(last)?
+    };
+
+    if end < start {
+        return Err(RangeError::Invalid);
+    }
+
+    if start >= total {
+        return Ok(None);
+    }
+
+    Ok(Some(ByteRange {
+        start,
+        end: end.min(total - 1),
+    }))
+}
+
+fn parse_position(s: &str) -> Result<u64, RangeError> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(RangeError::Invalid);
+    }
+
+    s.parse::<u64>().map_err(|_e| RangeError::Invalid)
+}
+
+/// Parses a `Content-Range: bytes start-end/total` value sent with a request
+/// body, where the total may be `*`. Returns None if the value is malformed.
+#[must_use]
+pub fn parse_content_range(value: &str) -> Option<ByteRange> {
+    let (unit, rest) = value.trim().split_once(' ')?;
+    if !unit.eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+
+    let (span, total) = rest.trim().split_once('/')?;
+    let (start, end) = span.split_once('-')?;
+    let start = parse_position(start).ok()?;
+    let end = parse_position(end).ok()?;
+    if end < start {
+        return None;
+    }
+
+    if total != "*" && parse_position(total).ok()? <= end {
+        return None;
+    }
+
+    Some(ByteRange { start, end })
+}
+
+/// Evaluates an If-Range precondition against the representation's
+/// validators. Returns true if the Range header should be honored.
+///
+/// An entity tag must match strongly; a date must equal the last
+/// modification time exactly.
+#[must_use]
+pub fn if_range_matches(value: &str, validators: &Validators) -> bool {
+    if let Some(tag) = EntityTag::parse(value) {
+        return validators
+            .etag
+            .as_ref()
+            .is_some_and(|current| current.strong_eq(&tag));
+    }
+
+    let Some(modified) = validators.last_modified else {
+        return false;
+    };
+
+    value
+        .trim()
+        .parse::<HttpDate>()
+        .is_ok_and(|date| date == HttpDate::from(modified))
+}
+
+/// Returns a boundary for a multipart/byteranges body.
+#[must_use]
+pub fn boundary() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    format!("RANGE_BOUNDARY_{nanos:x}")
+}
+
+/// Builds a multipart/byteranges body that reads each range from `file` on
+/// demand.
+#[must_use]
+pub fn multipart_body(
+    file: File,
+    ranges: &[ByteRange],
+    total: u64,
+    content_type: &str,
+    boundary: &str,
+) -> MultipartReader {
+    let mut segments = Vec::with_capacity(ranges.len() * 2 + 1);
+    for range in ranges {
+        let head = format!(
+            "\r\n--{boundary}\r\nContent-Type: {content_type}\r\nContent-Range: {}\r\n\r\n",
+            range.content_range(total)
+        );
+        segments.push(Segment::Bytes(head.into_bytes()));
+        segments.push(Segment::File(*range));
+    }
+    segments.push(Segment::Bytes(format!("\r\n--{boundary}--\r\n").into_bytes()));
+
+    MultipartReader {
+        file,
+        segments,
+        index: 0,
+        offset: 0,
+    }
+}
+
+enum Segment {
+    Bytes(Vec<u8>),
+    File(ByteRange),
+}
+
+/// Reads the parts of a multipart/byteranges body in order.
+pub struct MultipartReader {
+    file: File,
+    segments: Vec<Segment>,
+    index: usize,
+    offset: u64,
+}
+
+impl Read for MultipartReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        while let Some(segment) = self.segments.get(self.index) {
+            let n = match segment {
+                Segment::Bytes(bytes) => {
+                    let rest = &bytes[usize::try_from(self.offset).unwrap_or(usize::MAX)..];
+                    let n = rest.len().min(buf.len());
+                    buf[..n].copy_from_slice(&rest[..n]);
+                    n
+                }
+                Segment::File(range) => {
+                    let remaining = range.len() - self.offset;
+                    if remaining == 0 {
+                        0
+                    } else {
+                        self.file.seek(SeekFrom::Start(range.start + self.offset))?;
+                        let limit = usize::try_from(remaining).map_or(buf.len(), |r| r.min(buf.len()));
+                        let n = self.file.read(&mut buf[..limit])?;
+                        if n == 0 {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::UnexpectedEof,
+                                "file ended before the requested range",
+                            ));
+                        }
+                        n
+                    }
+                }
+            };
+
+            if n == 0 {
+                self.index += 1;
+                self.offset = 0;
+                continue;
+            }
+
+            self.offset += n as u64;
+            return Ok(n);
+        }
+
+        Ok(0)
+    }
+}
@@ -0,0 +1,189 @@
+use super::*;
+
+use std::time::Duration;
+
+// Tests for parse()
+#[test]
+fn test_parse_single_range() {
+    let ranges = parse("bytes=0-499", 1000).unwrap();
+    assert_eq!(ranges, vec![ByteRange { start: 0, end: 499 }]);
+    assert_eq!(ranges[0].len(), 500);
+}
+
+#[test]
+fn test_parse_open_ended_range() {
+    let ranges = parse("bytes=900-", 1000).unwrap();
+    assert_eq!(ranges, vec![ByteRange { start: 900, end: 999 }]);
+}
+
+#[test]
+fn test_parse_suffix_range() {
+    let ranges = parse("bytes=-100", 1000).unwrap();
+    assert_eq!(ranges, vec![ByteRange { start: 900, end: 999 }]);
+}
+
+#[test]
+fn test_parse_suffix_longer_than_representation() {
+    let ranges = parse("bytes=-5000", 1000).unwrap();
+    assert_eq!(ranges, vec![ByteRange { start: 0, end: 999 }]);
+}
+
+#[test]
+fn test_parse_range_past_end_is_truncated() {
+    let ranges = parse("bytes=500-5000", 1000).unwrap();
+    assert_eq!(ranges, vec![ByteRange { start: 500, end: 999 }]);
+}
+
+#[test]
+fn test_parse_multiple_ranges_keep_order() {
+    let ranges = parse("bytes=500-599, 0-99 ,-10", 1000).unwrap();
+    assert_eq!(
+        ranges,
+        vec![
+            ByteRange { start: 500, end: 599 },
+            ByteRange { start: 0, end: 99 },
+            ByteRange { start: 990, end: 999 },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_unit_is_case_insensitive() {
+    assert!(parse("Bytes=0-1", 10).is_ok());
+}
+
+#[test]
+fn test_parse_drops_unsatisfiable_ranges() {
+    let ranges = parse("bytes=2000-3000,0-0", 1000).unwrap();
+    assert_eq!(ranges, vec![ByteRange { start: 0, end: 0 }]);
+}
+
+#[test]
+fn test_parse_unsatisfiable() {
+    assert_eq!(parse("bytes=1000-", 1000), Err(RangeError::Unsatisfiable));
+    assert_eq!(parse("bytes=-0", 1000), Err(RangeError::Unsatisfiable));
+    assert_eq!(parse("bytes=0-10", 0), Err(RangeError::Unsatisfiable));
+}
+
+#[test]
+fn test_parse_invalid() {
+    assert_eq!(parse("items=0-10", 1000), Err(RangeError::Invalid));
+    assert_eq!(parse("bytes 0-10", 1000), Err(RangeError::Invalid));
+    assert_eq!(parse("bytes=", 1000), Err(RangeError::Invalid));
+    assert_eq!(parse("bytes=10-5", 1000), Err(RangeError::Invalid));
+    assert_eq!(parse("bytes=a-b", 1000), Err(RangeError::Invalid));
+    assert_eq!(parse("bytes=+1-5", 1000), Err(RangeError::Invalid));
+    assert_eq!(parse("bytes=-", 1000), Err(RangeError::Invalid));
+}
+
+#[test]
+fn test_parse_too_many_ranges_is_invalid() {
+    let specs = (0..=MAX_RANGES).map(|i| format!("{i}-{i}")).collect::<Vec<_>>();
+    let value = format!("bytes={}", specs.join(","));
+    assert_eq!(parse(&value, 1000), Err(RangeError::Invalid));
+}
+
+// Tests for Content-Range values
+#[test]
+fn test_content_range() {
+    let range = ByteRange { start: 10, end: 19 };
+    assert_eq!(range.content_range(100), "bytes 10-19/100");
+    assert_eq!(unsatisfied_content_range(100), "bytes */100");
+}
+
+// Tests for if_range_matches()
+fn validators(etag: Option<EntityTag>, secs: Option<u64>) -> Validators {
+    Validators {
+        etag,
+        last_modified: secs.map(|s| UNIX_EPOCH + Duration::from_secs(s)),
+    }
+}
+
+#[test]
+fn test_if_range_matches_same_date() {
+    let v = validators(None, Some(1_700_000_000));
+    let value = httpdate::fmt_http_date(v.last_modified.unwrap());
+    assert!(if_range_matches(&value, &v));
+}
+
+#[test]
+fn test_if_range_ignores_sub_second_precision() {
+    let modified = UNIX_EPOCH + Duration::from_millis(1_700_000_000_500);
+    let v = Validators {
+        etag: None,
+        last_modified: Some(modified),
+    };
+    assert!(if_range_matches(&httpdate::fmt_http_date(modified), &v));
+}
+
+#[test]
+fn test_if_range_does_not_match_other_date() {
+    let v = validators(None, Some(1_700_000_000));
+    let value = httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(1_699_999_999));
+    assert!(!if_range_matches(&value, &v));
+}
+
+#[test]
+fn test_if_range_does_not_match_unknown_time() {
+    let v = validators(None, None);
+    let value = httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+    assert!(!if_range_matches(&value, &v));
+}
+
+#[test]
+fn test_if_range_matches_strong_etag() {
+    let v = validators(Some(EntityTag::strong("abc")), Some(1_700_000_000));
+    assert!(if_range_matches("\"abc\"", &v));
+    assert!(!if_range_matches("\"xyz\"", &v));
+}
+
+#[test]
+fn test_if_range_never_matches_weak_etag() {
+    let v = validators(Some(EntityTag::weak("abc")), None);
+    assert!(!if_range_matches("W/\"abc\"", &v));
+    assert!(!if_range_matches("\"abc\"", &v));
+}
+
+// Tests for multipart_body()
+#[test]
+fn test_multipart_body() {
+    let path = std::env::temp_dir().join(format!("range-test-{}-multipart", std::process::id()));
+    std::fs::write(&path, b"0123456789").unwrap();
+    let file = File::open(&path).unwrap();
+
+    let ranges = [ByteRange { start: 0, end: 1 }, ByteRange { start: 7, end: 9 }];
+    let mut body = String::new();
+    multipart_body(file, &ranges, 10, "text/plain", "XYZ")
+        .read_to_string(&mut body)
+        .unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(
+        body,
+        "\r\n--XYZ\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\n01\
+         \r\n--XYZ\r\nContent-Type: text/plain\r\nContent-Range: bytes 7-9/10\r\n\r\n789\
+         \r\n--XYZ--\r\n"
+    );
+}
+
+// Tests for parse_content_range()
+#[test]
+fn test_parse_content_range() {
+    assert_eq!(
+        parse_content_range("bytes 10-19/*"),
+        Some(ByteRange { start: 10, end: 19 })
+    );
+    assert_eq!(
+        parse_content_range("bytes 0-0/100"),
+        Some(ByteRange { start: 0, end: 0 })
+    );
+}
+
+#[test]
+fn test_parse_content_range_invalid() {
+    assert_eq!(parse_content_range("bytes 10-19"), None);
+    assert_eq!(parse_content_range("bytes 19-10/*"), None);
+    assert_eq!(parse_content_range("bytes 10-19/15"), None);
+    assert_eq!(parse_content_range("items 0-1/*"), None);
+    assert_eq!(parse_content_range("bytes */100"), None);
+}
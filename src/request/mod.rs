@@ -2,24 +2,76 @@
 mod tests;
 
 use anyhow::{Result, anyhow};
+use std::fmt;
 use std::io::Read;
+use std::str::FromStr;
 
 use crate::body::HttpBody;
-use crate::connection::LineStream;
+use crate::connection::{LineStream, LineTooLong};
 use crate::consts;
 use crate::header::Headers;
 use crate::http::method::HttpMethod;
+use crate::http::uri;
 
 #[derive(Debug)]
 pub struct Request {
-    #[allow(dead_code)]
     method: HttpMethod,
+    /// The request-target as sent, still percent-encoded.
+    target: String,
+    /// The path of the target with its escapes decoded.
     path: String,
+    /// The decoded name and value pairs of the query, in order.
+    query: Vec<(String, String)>,
     headers: Headers,
     body: HttpBody,
+    /// The fields of a chunked body's trailer section, kept apart from the
+    /// header fields.
+    trailers: Headers,
+    /// The parameters captured by the pattern of the matched route.
+    params: Vec<(String, String)>,
 }
 
-/// Parses an HTTP request from a `LineStream`.
+/// The default longest request line in bytes.
+pub const DEFAULT_MAX_REQUEST_LINE: usize = 8 * 1024;
+
+/// The default most header fields in a request.
+pub const DEFAULT_MAX_HEADERS: usize = 100;
+
+/// The default most bytes of header fields in a request.
+pub const DEFAULT_MAX_HEADER_BYTES: usize = 64 * 1024;
+
+/// The default largest request body in bytes.
+pub const DEFAULT_MAX_BODY: usize = 64 * 1024 * 1024;
+
+/// Bounds on the size of a request, so a client can't make the server buffer
+/// arbitrary amounts of data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestLimits {
+    /// The longest request line in bytes, answered with 414 when exceeded.
+    pub max_request_line: usize,
+    /// The most header fields, trailer fields included, answered with 431.
+    pub max_headers: usize,
+    /// The most bytes of header fields in total, answered with 431.
+    pub max_header_bytes: usize,
+    /// The largest body in bytes as sent on the wire, answered with 413.
+    pub max_body: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_request_line: DEFAULT_MAX_REQUEST_LINE,
+            max_headers: DEFAULT_MAX_HEADERS,
+            max_header_bytes: DEFAULT_MAX_HEADER_BYTES,
+            max_body: DEFAULT_MAX_BODY,
+        }
+    }
+}
+
+/// The longest chunk-size line of a chunked body, extensions included.
+const MAX_CHUNK_SIZE_LINE: usize = 1024;
+
+/// Parses an HTTP request from a `LineStream` with the default limits.
 ///
 /// This function preserves the internal buffer state of the `LineStream`,
 /// making it suitable for parsing multiple pipelined requests from a single
@@ -34,37 +86,215 @@ pub struct Request {
 /// * `Ok(Request)` - Successfully parsed HTTP request
 /// * `Err(_)` - If the request is malformed or an I/O error occurs
 pub fn from_line_stream<T: Read>(ls: &mut LineStream<T>) -> Result<Request> {
-    let mut req: Option<Request> = None;
+    from_line_stream_limited(ls, &RequestLimits::default())
+}
+
+/// Parses an HTTP request from a `LineStream`, enforcing `limits` while
+/// reading so an oversized request is never buffered in full. A request over
+/// a limit fails with the matching `RequestError`.
+pub fn from_line_stream_limited<T: Read>(
+    ls: &mut LineStream<T>,
+    limits: &RequestLimits,
+) -> Result<Request> {
+    read_head(ls, limits)?.read_body(ls, limits)
+}
+
+/// Reads the request line and header section of a request, leaving the body
+/// in the stream.
+///
+/// Together with `RequestHead::read_body` this does what
+/// `from_line_stream_limited` does, but lets the caller act in between, e.g.
+/// to give the body a different read timeout.
+pub fn read_head<T: Read>(ls: &mut LineStream<T>, limits: &RequestLimits) -> Result<RequestHead> {
+    let line = ls
+        .read_line_limited(limits.max_request_line)
+        .map_err(|e| over_limit(e, RequestError::RequestLineTooLong))?;
+    if line.is_empty() {
+        return Err(anyhow!("unable to parse the request"));
+    }
 
+    let mut req = Request::from_request_line(&line)?;
+    let mut budget = HeaderBudget::new(limits);
     loop {
-        let current_line = ls.read_line()?;
+        let line = budget.read_line(ls)?;
 
-        // end of the headers, push the remaining bytes in the buffer to the left_over
-        // in case they're part of the request body
-        if current_line.is_empty() {
+        // end of the headers, the remaining bytes in the buffer may be part
+        // of the request body
+        if line.is_empty() {
             break;
         }
 
-        match req {
-            None => req = Some(Request::from_request_line(&current_line)?),
-            Some(ref mut r) => {
-                r.headers.read(&current_line)?;
-            }
-        }
+        req.headers.read(&line)?;
     }
 
-    req.map_or_else(
-        || Err(anyhow!("unable to parse the request")),
-        |mut req| {
-            let content_length = req.headers.content_length()?;
-            if content_length != 0 {
-                let body = ls.read_bytes(content_length)?;
+    Ok(RequestHead { req, budget })
+}
+
+/// A request whose header section has been read but whose body hasn't.
+pub struct RequestHead {
+    req: Request,
+    // a chunked trailer counts against what the header section left over
+    budget: HeaderBudget,
+}
+
+impl RequestHead {
+    /// Reads the body framed by the header section and returns the complete
+    /// request.
+    pub fn read_body<T: Read>(
+        mut self,
+        ls: &mut LineStream<T>,
+        limits: &RequestLimits,
+    ) -> Result<Request> {
+        let req = &mut self.req;
+        let transfer_encodings = req.headers.transfer_encodings();
+        if !transfer_encodings.is_empty() {
+            // a message with both framings could be used to smuggle requests
+            if req.headers.has_content_length() {
+                return Err(RequestError::ConflictingFraming.into());
+            }
+
+            if transfer_encodings != ["chunked"] {
+                return Err(
+                    RequestError::UnsupportedTransferCoding(transfer_encodings.join(", ")).into(),
+                );
+            }
+
+            let body = read_chunked_body(ls, &mut req.trailers, limits.max_body, &mut self.budget)?;
+            if !body.is_empty() {
                 req.body = HttpBody::Content(body);
             }
 
-            Ok(req)
-        },
-    )
+            return Ok(self.req);
+        }
+
+        let content_length = req.headers.content_length()?;
+        if content_length > limits.max_body {
+            return Err(RequestError::BodyTooLarge.into());
+        }
+        if content_length != 0 {
+            let body = ls.read_bytes(content_length)?;
+            req.body = HttpBody::Content(body);
+        }
+
+        Ok(self.req)
+    }
+}
+
+/// Tracks how many header fields and bytes a request may still send.
+struct HeaderBudget {
+    fields: usize,
+    bytes: usize,
+}
+
+impl HeaderBudget {
+    const fn new(limits: &RequestLimits) -> Self {
+        Self {
+            fields: limits.max_headers,
+            bytes: limits.max_header_bytes,
+        }
+    }
+
+    /// Reads a header line, or the empty line that ends the section, and
+    /// charges it to the budget.
+    fn read_line<T: Read>(&mut self, ls: &mut LineStream<T>) -> Result<Vec<u8>> {
+        let line = ls
+            .read_line_limited(self.bytes)
+            .map_err(|e| over_limit(e, RequestError::HeadersTooLarge))?;
+        if line.is_empty() {
+            return Ok(line);
+        }
+
+        // the CRLF counts too, so a flood of tiny fields runs out as well
+        self.bytes = self
+            .bytes
+            .checked_sub(line.len() + 2)
+            .ok_or(RequestError::HeadersTooLarge)?;
+        self.fields = self
+            .fields
+            .checked_sub(1)
+            .ok_or(RequestError::HeadersTooLarge)?;
+        Ok(line)
+    }
+}
+
+/// Replaces a `LineTooLong` error with the `RequestError` for the limit.
+fn over_limit(e: anyhow::Error, error: RequestError) -> anyhow::Error {
+    if e.is::<LineTooLong>() {
+        error.into()
+    } else {
+        e
+    }
+}
+
+/// Decodes a body sent with `Transfer-Encoding: chunked`.
+///
+/// Chunk extensions are ignored. Fields in the trailer section are stored in
+/// `trailers` rather than merged into the header fields, where they could
+/// change how the request is handled, and count against the same budget as
+/// the header section.
+///
+/// # Returns
+///
+/// * `Ok(Vec<u8>)` - The concatenated chunk data
+/// * `Err(_)` - If a chunk is malformed or an I/O error occurs
+fn read_chunked_body<T: Read>(
+    ls: &mut LineStream<T>,
+    trailers: &mut Headers,
+    max_body: usize,
+    budget: &mut HeaderBudget,
+) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+
+    loop {
+        let line = ls
+            .read_line_limited(MAX_CHUNK_SIZE_LINE)
+            .map_err(|e| over_limit(e, invalid_chunk("chunk-size line is too long")))?;
+        let size = parse_chunk_size(&line)?;
+        if size == 0 {
+            break;
+        }
+
+        if size > max_body - body.len() {
+            return Err(RequestError::BodyTooLarge.into());
+        }
+        body.extend(ls.read_bytes(size)?);
+
+        // exactly a CRLF follows the data, so nothing else gets buffered
+        if ls.read_bytes(consts::CRLF.len())? != consts::CRLF {
+            return Err(invalid_chunk("chunk data is not terminated by CRLF").into());
+        }
+    }
+
+    loop {
+        let line = budget.read_line(ls)?;
+        if line.is_empty() {
+            break;
+        }
+
+        trailers.read(&line)?;
+    }
+
+    Ok(body)
+}
+
+fn invalid_chunk(msg: &str) -> RequestError {
+    RequestError::InvalidChunk(String::from(msg))
+}
+
+/// Parses the chunk-size line of a chunked body, ignoring any chunk extensions.
+fn parse_chunk_size(line: &[u8]) -> Result<usize> {
+    let line = std::str::from_utf8(line)?;
+    let size = line
+        .split_once(';')
+        .map_or(line, |(size, _ext)| size)
+        .trim();
+
+    if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid_chunk(&format!("invalid chunk size: {size}")).into());
+    }
+
+    usize::from_str_radix(size, 16)
+        .map_err(|_e| invalid_chunk(&format!("invalid chunk size: {size}")).into())
 }
 
 /// Parses an HTTP request from a reader.
@@ -81,7 +311,6 @@ pub fn from_line_stream<T: Read>(ls: &mut LineStream<T>) -> Result<Request> {
 ///
 /// * `Ok(Request)` - Successfully parsed HTTP request
 /// * `Err(_)` - If the request is malformed or an I/O error occurs
-#[allow(dead_code)]
 pub fn from_reader(stream: &mut impl Read) -> Result<Request> {
     let mut ls = LineStream::new(stream);
     from_line_stream(&mut ls)
@@ -91,39 +320,240 @@ impl Request {
     fn from_request_line(bytes: &[u8]) -> Result<Self> {
         let rl = RequestLine::from_bytes(bytes)?;
         let method = rl.method.parse()?;
-        let path = String::from(rl.path);
+        let (path, query) = uri::split_target(rl.path);
+        let path = uri::decode_path(path).map_err(RequestError::InvalidTarget)?;
 
         Ok(Self {
             method,
+            target: String::from(rl.path),
             path,
+            query: query.map(uri::parse_query).unwrap_or_default(),
             headers: Headers::new(),
             body: HttpBody::Empty,
+            trailers: Headers::new(),
+            params: Vec::new(),
         })
     }
 
-    pub fn path_match_exact(&self, pattern: &str) -> bool {
-        pattern == self.path
+    /// Returns the path of the request-target with its escapes decoded,
+    /// e.g. `/files/my file.txt` for `/files/my%20file.txt?x=1`.
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
     }
 
-    pub fn path_match_prefix(&self, pattern: &str) -> bool {
-        self.path.starts_with(pattern)
+    /// Returns the path of the request-target still percent-encoded, e.g.
+    /// `/files/my%20file.txt` for `/files/my%20file.txt?x=1`.
+    #[must_use]
+    pub fn raw_path(&self) -> &str {
+        uri::split_target(&self.target).0
     }
 
-    pub fn path(&self) -> &str {
-        &self.path
+    /// Returns the request-target exactly as the client sent it.
+    #[must_use]
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Returns the query of the request-target without the `?`, still
+    /// percent-encoded.
+    #[must_use]
+    pub fn query_string(&self) -> Option<&str> {
+        uri::split_target(&self.target).1
+    }
+
+    /// Returns the decoded name and value pairs of the query, in order.
+    #[must_use]
+    pub fn query_pairs(&self) -> &[(String, String)] {
+        &self.query
+    }
+
+    /// Returns the first value of a query parameter.
+    #[must_use]
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns all values of a query parameter that may be repeated, e.g.
+    /// `["a", "b"]` for `tag=a&tag=b`.
+    #[must_use]
+    pub fn query_all(&self, name: &str) -> Vec<&str> {
+        self.query
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    /// Parses the first value of a query parameter. Returns None if the
+    /// parameter is missing.
+    pub fn query_as<T: FromStr>(&self, name: &str) -> Option<Result<T, T::Err>> {
+        self.query(name).map(str::parse)
     }
 
+    #[must_use]
     pub const fn method(&self) -> &HttpMethod {
         &self.method
     }
 
+    #[must_use]
     pub const fn headers(&self) -> &Headers {
         &self.headers
     }
 
+    #[must_use]
     pub const fn body(&self) -> &HttpBody {
         &self.body
     }
+
+    /// Returns the trailer fields sent after a chunked body. They aren't
+    /// part of `headers`.
+    #[must_use]
+    pub const fn trailers(&self) -> &Headers {
+        &self.trailers
+    }
+
+    /// Returns the value of a parameter captured from the path by the
+    /// matched route, e.g. `id` for the pattern `/users/:id`.
+    #[must_use]
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_params(&mut self, params: Vec<(String, String)>) {
+        self.params = params;
+    }
+
+    /// Decodes a body sent with `Content-Encoding`, so handlers see the
+    /// content as the client meant it. The codings are undone in reverse
+    /// order and the Content-Encoding header is removed afterwards.
+    ///
+    /// Decoding stops with an error once the content grows past `limit`
+    /// bytes, so a small compressed body can't exhaust memory.
+    pub fn decode_body(&mut self, limit: usize) -> Result<(), ContentDecodingError> {
+        let codings = self.headers.content_encodings();
+        if let Some(unknown) = codings
+            .iter()
+            .find(|c| *c != "identity" && !DECODABLE_CODINGS.contains(&c.as_str()))
+        {
+            return Err(ContentDecodingError::Unsupported(unknown.clone()));
+        }
+
+        if codings.is_empty() {
+            return Ok(());
+        }
+
+        if let HttpBody::Content(data) = &self.body {
+            let mut decoded = data.clone();
+            for coding in codings.iter().rev() {
+                decoded = decode(coding, &decoded, limit)?;
+            }
+
+            self.headers.set_content_length(decoded.len());
+            self.body = HttpBody::Content(decoded);
+        }
+
+        self.headers.remove(consts::HEADER_CONTENT_ENCODING);
+        Ok(())
+    }
+}
+
+/// The content codings a request body can be sent with. `x-gzip` is an alias
+/// of gzip.
+const DECODABLE_CODINGS: [&str; 5] = ["gzip", "x-gzip", "deflate", "br", "zstd"];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ContentDecodingError {
+    /// The body was sent with a content coding the server can't decode.
+    Unsupported(String),
+    /// The decoded body is larger than the limit.
+    TooLarge(usize),
+    /// The body is not valid for its content coding.
+    Corrupt(String),
+}
+
+impl fmt::Display for ContentDecodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unsupported(coding) => write!(f, "unsupported content coding: {coding}"),
+            Self::TooLarge(limit) => write!(f, "decoded body is larger than {limit} bytes"),
+            Self::Corrupt(msg) => write!(f, "failed to decode body: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ContentDecodingError {}
+
+/// A malformed request that the server answers with an error status before
+/// closing the connection.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RequestError {
+    /// The request-target can't be decoded.
+    InvalidTarget(String),
+    /// The request line is longer than allowed.
+    RequestLineTooLong,
+    /// The header section has too many fields or bytes.
+    HeadersTooLarge,
+    /// The body is larger than allowed.
+    BodyTooLarge,
+    /// The chunked framing of the body is malformed.
+    InvalidChunk(String),
+    /// The request has both Content-Length and Transfer-Encoding.
+    ConflictingFraming,
+    /// The request uses a transfer coding other than chunked.
+    UnsupportedTransferCoding(String),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTarget(msg) => write!(f, "invalid request-target: {msg}"),
+            Self::RequestLineTooLong => write!(f, "request line is too long"),
+            Self::HeadersTooLarge => write!(f, "header section is too large"),
+            Self::BodyTooLarge => write!(f, "request body is too large"),
+            Self::InvalidChunk(msg) => write!(f, "invalid chunked body: {msg}"),
+            Self::ConflictingFraming => {
+                write!(f, "request has both Content-Length and Transfer-Encoding")
+            }
+            Self::UnsupportedTransferCoding(codings) => {
+                write!(f, "unsupported transfer coding: {codings}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+/// Undoes one content coding, reading at most `limit` bytes of output.
+fn decode(coding: &str, data: &[u8], limit: usize) -> Result<Vec<u8>, ContentDecodingError> {
+    let corrupt = |e: std::io::Error| ContentDecodingError::Corrupt(format!("{coding}: {e}"));
+    let decoder: Box<dyn Read + '_> = match coding {
+        "identity" => return Ok(Vec::from(data)),
+        "gzip" | "x-gzip" => Box::new(flate2::read::MultiGzDecoder::new(data)),
+        "deflate" => Box::new(flate2::read::ZlibDecoder::new(data)),
+        "br" => Box::new(brotli::Decompressor::new(data, 4096)),
+        "zstd" => Box::new(zstd::stream::read::Decoder::new(data).map_err(corrupt)?),
+        _ => return Err(ContentDecodingError::Unsupported(String::from(coding))),
+    };
+
+    // read one byte past the limit to tell a body of exactly `limit` bytes
+    // from a larger one
+    let mut content = Vec::new();
+    decoder
+        .take(limit as u64 + 1)
+        .read_to_end(&mut content)
+        .map_err(corrupt)?;
+    if content.len() > limit {
+        return Err(ContentDecodingError::TooLarge(limit));
+    }
+
+    Ok(content)
 }
 
 struct RequestLine<'a> {
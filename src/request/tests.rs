@@ -1,6 +1,9 @@
-use std::io::Cursor;
+use std::io::{Cursor, Read};
 
-use super::{from_line_stream, from_reader};
+use super::{
+    ContentDecodingError, Request, RequestError, RequestLimits, from_line_stream,
+    from_line_stream_limited, from_reader, read_head,
+};
 use crate::body::HttpBody;
 use crate::connection::LineStream;
 
@@ -40,7 +43,7 @@ fn test_from_reader_with_body() {
     assert_eq!(request.path(), "/submit");
     match request.body() {
         HttpBody::Content(data) => assert_eq!(data, body),
-        HttpBody::Empty => panic!("Expected Content, got Empty"),
+        other => panic!("Expected Content, got {other:?}"),
     }
 }
 
@@ -59,7 +62,7 @@ fn test_from_reader_with_json_body() {
     assert_eq!(request.path(), "/api/data");
     match request.body() {
         HttpBody::Content(data) => assert_eq!(data, body),
-        HttpBody::Empty => panic!("Expected Content, got Empty"),
+        other => panic!("Expected Content, got {other:?}"),
     }
 }
 
@@ -81,7 +84,7 @@ fn test_from_reader_with_large_body() {
     assert_eq!(request.path(), "/upload");
     match request.body() {
         HttpBody::Content(data) => assert_eq!(data.len(), 2500),
-        HttpBody::Empty => panic!("Expected Content, got Empty"),
+        other => panic!("Expected Content, got {other:?}"),
     }
 }
 
@@ -112,7 +115,7 @@ fn test_from_reader_body_with_binary_data() {
     assert_eq!(request.path(), "/binary");
     match request.body() {
         HttpBody::Content(data) => assert_eq!(data, &body),
-        HttpBody::Empty => panic!("Expected Content, got Empty"),
+        other => panic!("Expected Content, got {other:?}"),
     }
 }
 
@@ -211,7 +214,7 @@ fn test_from_line_stream_pipelined_with_body() {
     assert_eq!(req1.path(), "/submit");
     match req1.body() {
         HttpBody::Content(data) => assert_eq!(data, body1),
-        HttpBody::Empty => panic!("Expected Content, got Empty"),
+        other => panic!("Expected Content, got {other:?}"),
     }
 
     // Parse second request (GET)
@@ -263,7 +266,7 @@ fn test_from_line_stream_pipelined_with_large_body() {
             assert_eq!(data.len(), 1500);
             assert_eq!(data, &body);
         }
-        HttpBody::Empty => panic!("Expected Content, got Empty"),
+        other => panic!("Expected Content, got {other:?}"),
     }
 
     // Parse second request
@@ -290,7 +293,7 @@ fn test_from_line_stream_pipelined_mixed_methods() {
     assert_eq!(req2.path(), "/resource");
     match req2.body() {
         HttpBody::Content(data) => assert_eq!(data, body),
-        HttpBody::Empty => panic!("Expected Content, got Empty"),
+        other => panic!("Expected Content, got {other:?}"),
     }
 
     let req3 = from_line_stream(&mut ls).expect("should parse DELETE request");
@@ -307,3 +310,437 @@ fn test_from_reader_with_accept_encoding() {
     let values = request.headers().accept_encodings().unwrap();
     assert_eq!(values, vec!["gzip".to_string(), "deflate".to_string()]);
 }
+
+#[test]
+fn test_from_reader_chunked_body() {
+    let raw_request =
+        b"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n8\r\n, World!\r\n0\r\n\r\n";
+    let mut reader = Cursor::new(raw_request.as_slice());
+
+    let request = from_reader(&mut reader).expect("should parse request");
+
+    match request.body() {
+        HttpBody::Content(data) => assert_eq!(data, b"Hello, World!"),
+        other => panic!("Expected Content, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_from_reader_chunked_body_with_extensions_and_uppercase_size() {
+    let raw_request = b"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nA;name=value\r\n0123456789\r\n0;last\r\n\r\n";
+    let mut reader = Cursor::new(raw_request.as_slice());
+
+    let request = from_reader(&mut reader).expect("should parse request");
+
+    match request.body() {
+        HttpBody::Content(data) => assert_eq!(data, b"0123456789"),
+        other => panic!("Expected Content, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_from_reader_chunked_body_with_trailers() {
+    let raw_request = b"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\nX-Checksum: 42\r\nContent-Length: 99\r\n\r\n";
+    let mut reader = Cursor::new(raw_request.as_slice());
+
+    let request = from_reader(&mut reader).expect("should parse request");
+
+    assert_eq!(request.trailers().get("X-Checksum"), Some("42"));
+    // trailer fields don't change the header section
+    assert_eq!(request.headers().get("X-Checksum"), None);
+    assert_eq!(request.headers().get("Content-Length"), None);
+}
+
+#[test]
+fn test_from_reader_chunked_trailers_dont_affect_decoding() {
+    let raw_request = b"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\nContent-Encoding: gzip\r\n\r\n";
+    let mut request = from_reader(&mut Cursor::new(raw_request.as_slice())).unwrap();
+
+    assert_eq!(request.trailers().get("Content-Encoding"), Some("gzip"));
+    request.decode_body(1024).unwrap();
+    match request.body() {
+        HttpBody::Content(data) => assert_eq!(data, b"abc"),
+        other => panic!("Expected Content, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_from_reader_chunked_empty_body() {
+    let raw_request = b"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+    let mut reader = Cursor::new(raw_request.as_slice());
+
+    let request = from_reader(&mut reader).expect("should parse request");
+
+    assert!(matches!(request.body(), HttpBody::Empty));
+}
+
+#[test]
+fn test_from_reader_chunked_large_body_across_buffers() {
+    #[allow(clippy::cast_possible_truncation)]
+    let body: Vec<u8> = (0_u32..3000).map(|i| (i % 251) as u8).collect();
+    let mut raw_request = b"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+    for chunk in body.chunks(700) {
+        raw_request.extend(format!("{:x}\r\n", chunk.len()).as_bytes());
+        raw_request.extend(chunk);
+        raw_request.extend(b"\r\n");
+    }
+    raw_request.extend(b"0\r\n\r\n");
+    let mut reader = Cursor::new(raw_request);
+
+    let request = from_reader(&mut reader).expect("should parse request");
+
+    match request.body() {
+        HttpBody::Content(data) => assert_eq!(data, &body),
+        other => panic!("Expected Content, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_from_line_stream_pipelined_after_chunked() {
+    let raw_request = b"POST /first HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\ndata\r\n0\r\n\r\nGET /second HTTP/1.1\r\n\r\n";
+    let mut reader = Cursor::new(raw_request.as_slice());
+    let mut ls = LineStream::new(&mut reader);
+
+    let first = from_line_stream(&mut ls).expect("should parse first request");
+    let second = from_line_stream(&mut ls).expect("should parse second request");
+
+    assert_eq!(first.path(), "/first");
+    assert_eq!(second.path(), "/second");
+    assert!(matches!(second.body(), HttpBody::Empty));
+}
+
+#[test]
+fn test_from_reader_rejects_content_length_with_transfer_encoding() {
+    let raw_request = b"POST /upload HTTP/1.1\r\nContent-Length: 4\r\nTransfer-Encoding: chunked\r\n\r\n4\r\ndata\r\n0\r\n\r\n";
+    let mut reader = Cursor::new(raw_request.as_slice());
+
+    let err = from_reader(&mut reader).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<RequestError>(),
+        Some(RequestError::ConflictingFraming)
+    ));
+}
+
+#[test]
+fn test_from_reader_rejects_unsupported_transfer_coding() {
+    let raw_request = b"POST /upload HTTP/1.1\r\nTransfer-Encoding: gzip\r\n\r\n";
+    let mut reader = Cursor::new(raw_request.as_slice());
+
+    let err = from_reader(&mut reader).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<RequestError>(),
+        Some(RequestError::UnsupportedTransferCoding(codings)) if codings == "gzip"
+    ));
+}
+
+#[test]
+fn test_from_reader_rejects_invalid_chunk_size() {
+    let raw_request =
+        b"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\ndata\r\n0\r\n\r\n";
+    let mut reader = Cursor::new(raw_request.as_slice());
+
+    assert!(from_reader(&mut reader).is_err());
+}
+
+#[test]
+fn test_from_reader_rejects_chunk_without_crlf() {
+    let raw_request =
+        b"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\ndata\r\n0\r\n\r\n";
+    let mut reader = Cursor::new(raw_request.as_slice());
+
+    assert!(from_reader(&mut reader).is_err());
+}
+
+fn encoded_request(content_encoding: &str, body: &[u8]) -> Request {
+    let mut raw_request = format!(
+        "POST /files/a HTTP/1.1\r\nContent-Encoding: {content_encoding}\r\nContent-Length: {}\r\n\r\n",
+        body.len()
+    )
+    .into_bytes();
+    raw_request.extend_from_slice(body);
+
+    from_reader(&mut Cursor::new(raw_request)).expect("should parse request")
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn decoded_body(request: &Request) -> &[u8] {
+    match request.body() {
+        HttpBody::Content(data) => data,
+        other => panic!("Expected Content, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_decode_body_gzip() {
+    let mut request = encoded_request("gzip", &gzip(b"hello world"));
+
+    request.decode_body(1024).unwrap();
+
+    assert_eq!(decoded_body(&request), b"hello world");
+    assert!(request.headers().get("Content-Encoding").is_none());
+    assert_eq!(request.headers().content_length().unwrap(), 11);
+}
+
+#[test]
+fn test_decode_body_other_codings() {
+    use std::io::Write;
+
+    let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    zlib.write_all(b"deflated").unwrap();
+    let mut request = encoded_request("deflate", &zlib.finish().unwrap());
+    request.decode_body(1024).unwrap();
+    assert_eq!(decoded_body(&request), b"deflated");
+
+    let mut br = Vec::new();
+    brotli::CompressorWriter::new(&mut br, 4096, 5, 22)
+        .write_all(b"brotli")
+        .unwrap();
+    let mut request = encoded_request("br", &br);
+    request.decode_body(1024).unwrap();
+    assert_eq!(decoded_body(&request), b"brotli");
+
+    let zstd = zstd::encode_all(b"zstd".as_slice(), 3).unwrap();
+    let mut request = encoded_request("zstd", &zstd);
+    request.decode_body(1024).unwrap();
+    assert_eq!(decoded_body(&request), b"zstd");
+}
+
+#[test]
+fn test_decode_body_undoes_codings_in_reverse_order() {
+    let twice = gzip(&gzip(b"layered"));
+    let mut request = encoded_request("identity, gzip, x-gzip", &twice);
+
+    request.decode_body(1024).unwrap();
+
+    assert_eq!(decoded_body(&request), b"layered");
+}
+
+#[test]
+fn test_decode_body_without_content_encoding_is_untouched() {
+    let raw_request = b"POST /files/a HTTP/1.1\r\nContent-Length: 3\r\n\r\nabc";
+    let mut request = from_reader(&mut Cursor::new(raw_request.as_slice())).unwrap();
+
+    request.decode_body(1).unwrap();
+
+    assert_eq!(decoded_body(&request), b"abc");
+}
+
+#[test]
+fn test_decode_body_rejects_unknown_coding() {
+    let mut request = encoded_request("gzip, compress", b"data");
+
+    assert_eq!(
+        request.decode_body(1024),
+        Err(ContentDecodingError::Unsupported(String::from("compress")))
+    );
+}
+
+#[test]
+fn test_decode_body_enforces_limit() {
+    let body = vec![b'a'; 1000];
+    let mut request = encoded_request("gzip", &gzip(&body));
+    assert_eq!(
+        request.decode_body(999),
+        Err(ContentDecodingError::TooLarge(999))
+    );
+
+    let mut request = encoded_request("gzip", &gzip(&body));
+    request.decode_body(1000).unwrap();
+    assert_eq!(decoded_body(&request).len(), 1000);
+}
+
+#[test]
+fn test_decode_body_rejects_corrupt_data() {
+    let mut request = encoded_request("gzip", b"not gzip");
+
+    assert!(matches!(
+        request.decode_body(1024),
+        Err(ContentDecodingError::Corrupt(_))
+    ));
+}
+
+#[test]
+fn test_from_reader_decodes_path_and_query() {
+    let raw_request = b"GET /files/my%20file.txt?tag=a&tag=b+c&n=42 HTTP/1.1\r\n\r\n";
+    let request = from_reader(&mut Cursor::new(raw_request.as_slice())).unwrap();
+
+    assert_eq!(request.path(), "/files/my file.txt");
+    assert_eq!(request.target(), "/files/my%20file.txt?tag=a&tag=b+c&n=42");
+    assert_eq!(request.query_string(), Some("tag=a&tag=b+c&n=42"));
+    assert_eq!(request.query("tag"), Some("a"));
+    assert_eq!(request.query_all("tag"), ["a", "b c"]);
+    assert_eq!(request.query_as::<u32>("n"), Some(Ok(42)));
+    assert!(request.query_as::<u32>("tag").unwrap().is_err());
+    assert_eq!(request.query("missing"), None);
+    assert!(request.query_all("missing").is_empty());
+    assert_eq!(request.query_pairs().len(), 3);
+}
+
+#[test]
+fn test_from_reader_without_query() {
+    let raw_request = b"GET /echo/abc HTTP/1.1\r\n\r\n";
+    let request = from_reader(&mut Cursor::new(raw_request.as_slice())).unwrap();
+
+    assert_eq!(request.path(), "/echo/abc");
+    assert_eq!(request.query_string(), None);
+    assert!(request.query_pairs().is_empty());
+}
+
+#[test]
+fn test_from_reader_keeps_encoded_slash_in_raw_path() {
+    let raw_request = b"GET /items/a%2Fb?x=1 HTTP/1.1\r\n\r\n";
+    let request = from_reader(&mut Cursor::new(raw_request.as_slice())).unwrap();
+
+    assert_eq!(request.path(), "/items/a/b");
+    assert_eq!(request.raw_path(), "/items/a%2Fb");
+}
+
+#[test]
+fn test_from_reader_rejects_undecodable_target() {
+    let raw_request = b"GET /files/a%zz HTTP/1.1\r\n\r\n";
+    let err = from_reader(&mut Cursor::new(raw_request.as_slice())).unwrap_err();
+
+    assert!(matches!(
+        err.downcast_ref::<RequestError>(),
+        Some(RequestError::InvalidTarget(_))
+    ));
+}
+
+fn parse_limited(raw_request: &[u8], limits: &RequestLimits) -> anyhow::Result<Request> {
+    let mut reader = Cursor::new(raw_request);
+    from_line_stream_limited(&mut LineStream::new(&mut reader), limits)
+}
+
+fn limit_error(result: anyhow::Result<Request>) -> RequestError {
+    result
+        .unwrap_err()
+        .downcast::<RequestError>()
+        .expect("should fail with a RequestError")
+}
+
+#[test]
+fn test_from_line_stream_limited_request_line() {
+    let limits = RequestLimits {
+        max_request_line: 20,
+        ..RequestLimits::default()
+    };
+
+    assert!(parse_limited(b"GET /abc HTTP/1.1\r\n\r\n", &limits).is_ok());
+    let result = parse_limited(b"GET /abcdefghijklmnop HTTP/1.1\r\n\r\n", &limits);
+    assert!(matches!(
+        limit_error(result),
+        RequestError::RequestLineTooLong
+    ));
+}
+
+#[test]
+fn test_from_line_stream_limited_header_count() {
+    let limits = RequestLimits {
+        max_headers: 2,
+        ..RequestLimits::default()
+    };
+
+    assert!(parse_limited(b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\n\r\n", &limits).is_ok());
+    let result = parse_limited(b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n", &limits);
+    assert!(matches!(limit_error(result), RequestError::HeadersTooLarge));
+}
+
+#[test]
+fn test_from_line_stream_limited_header_bytes() {
+    // each field is charged with its CRLF, so "A: 1" and "B: 2" take 12 bytes
+    let limits = RequestLimits {
+        max_header_bytes: 12,
+        ..RequestLimits::default()
+    };
+
+    assert!(parse_limited(b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\n\r\n", &limits).is_ok());
+    let result = parse_limited(b"GET / HTTP/1.1\r\nA: 1\r\nB: 23\r\n\r\n", &limits);
+    assert!(matches!(limit_error(result), RequestError::HeadersTooLarge));
+}
+
+#[test]
+fn test_from_line_stream_limited_content_length() {
+    let limits = RequestLimits {
+        max_body: 4,
+        ..RequestLimits::default()
+    };
+
+    let request = parse_limited(b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nabcd", &limits);
+    assert!(request.is_ok());
+    // the body isn't read, so it doesn't even have to be sent
+    let result = parse_limited(
+        b"POST / HTTP/1.1\r\nContent-Length: 1000000\r\n\r\n",
+        &limits,
+    );
+    assert!(matches!(limit_error(result), RequestError::BodyTooLarge));
+}
+
+#[test]
+fn test_from_line_stream_limited_chunked_body() {
+    let limits = RequestLimits {
+        max_body: 4,
+        ..RequestLimits::default()
+    };
+    let raw_request = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+        2\r\nab\r\n2\r\ncd\r\n1\r\ne\r\n0\r\n\r\n";
+
+    let result = parse_limited(raw_request, &limits);
+    assert!(matches!(limit_error(result), RequestError::BodyTooLarge));
+}
+
+#[test]
+fn test_from_line_stream_limited_trailers_share_header_budget() {
+    let limits = RequestLimits {
+        max_headers: 2,
+        ..RequestLimits::default()
+    };
+    let raw_request = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+        1\r\na\r\n0\r\nX-One: 1\r\nX-Two: 2\r\n\r\n";
+
+    let result = parse_limited(raw_request, &limits);
+    assert!(matches!(limit_error(result), RequestError::HeadersTooLarge));
+}
+
+#[test]
+fn test_read_head_leaves_body_in_stream() {
+    let raw_request = b"POST /submit HTTP/1.1\r\nContent-Length: 5\r\n\r\nHello";
+    let mut reader = Cursor::new(raw_request.as_slice());
+    let mut ls = LineStream::new(&mut reader);
+    let limits = RequestLimits::default();
+
+    let head = read_head(&mut ls, &limits).expect("should parse the head");
+    let request = head
+        .read_body(&mut ls, &limits)
+        .expect("should read the body");
+
+    assert_eq!(request.path(), "/submit");
+    assert!(matches!(request.body(), HttpBody::Content(body) if body == b"Hello"));
+}
+
+#[test]
+fn test_from_line_stream_rejects_endless_line_after_chunk_data() {
+    // the line after the data never ends; it must be rejected, not buffered
+    let head = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc".as_slice();
+    let mut reader = head.chain(std::io::repeat(b'a'));
+    let mut ls = LineStream::new(&mut reader);
+
+    let result = from_line_stream(&mut ls);
+    assert!(matches!(limit_error(result), RequestError::InvalidChunk(_)));
+}
+
+#[test]
+fn test_from_line_stream_rejects_endless_chunk_size_line() {
+    let head = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n".as_slice();
+    let mut reader = head.chain(std::io::repeat(b'1'));
+    let mut ls = LineStream::new(&mut reader);
+
+    let result = from_line_stream(&mut ls);
+    assert!(matches!(limit_error(result), RequestError::InvalidChunk(_)));
+}
@@ -2,22 +2,42 @@
 mod tests;
 
 use crate::body::HttpBody;
-use crate::consts::{CRLF, HEADER_CONNECTION, HEADER_CONTENT_ENCODING};
+use crate::compression::CompressionLevel;
+use crate::consts::{
+    CRLF, HEADER_ACCEPT_ENCODING, HEADER_CONNECTION, HEADER_CONTENT_ENCODING,
+    HEADER_CONTENT_LENGTH, HEADER_CONTENT_RANGE, HEADER_CONTENT_TYPE, HEADER_TRANSFER_ENCODING,
+    HEADER_VARY,
+};
 use crate::header::Headers;
 use crate::http::status::HttpStatus;
 use anyhow::Result;
-use flate2::write::GzEncoder;
-use flate2::Compression;
-use std::io::Write;
+use std::fs::File;
+use std::io::{Cursor, Read, Write};
+
+/// The size of the buffer used to read a streamed body. Each read becomes at
+/// most one chunk on the wire.
+const STREAM_CHUNK_SIZE: usize = 8192;
+
+/// The content codings `compress` can apply, in the order the server prefers
+/// them when a client accepts several equally.
+pub const CONTENT_CODINGS: &[&str] = &["br", "zstd", "gzip", "deflate"];
+
+const BROTLI_WINDOW: u32 = 22;
+const BROTLI_BUFFER_SIZE: usize = 4096;
 
 #[derive(Debug)]
 pub struct Response {
     status: HttpStatus,
     headers: Headers,
     body: HttpBody,
+    /// Whether the body may be compressed before it is sent.
+    compressible: bool,
+    /// Whether `write` leaves out the body, as in the response to HEAD.
+    omit_body: bool,
 }
 
 impl Response {
+    #[must_use]
     pub fn new(status: HttpStatus) -> Self {
         let mut headers = Headers::new();
         headers.set(HEADER_CONNECTION, "keep-alive");
@@ -26,20 +46,77 @@ impl Response {
             status,
             headers,
             body: HttpBody::Empty,
+            compressible: true,
+            omit_body: false,
         }
     }
 
+    /// Returns the value of a header, if set.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// Returns the value of the Content-Type header, if set.
+    #[must_use]
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get(HEADER_CONTENT_TYPE)
+    }
+
+    /// Returns the length of the body, or None if it is streamed.
+    #[must_use]
+    pub fn body_len(&self) -> Option<usize> {
+        self.body.len()
+    }
+
+    /// Opts this response out of compression, e.g. because the body is
+    /// already compressed or must be sent byte for byte.
+    pub const fn disable_compression(&mut self) {
+        self.compressible = false;
+    }
+
+    #[must_use]
+    pub const fn is_compressible(&self) -> bool {
+        self.compressible
+    }
+
+    /// Makes `write` send the headers, including those that describe the
+    /// body, but not the body itself. This is how a HEAD request is answered.
+    pub const fn omit_body(&mut self) {
+        self.omit_body = true;
+    }
+
     pub fn set_str_body(&mut self, body: &str) {
-        let bytes = body.as_bytes();
-        self.headers.set("Content-Type", "text/plain");
-        self.headers.set_content_length(bytes.len());
-        self.body = HttpBody::Content(Vec::from(bytes));
+        self.set_body("text/plain", HttpBody::Content(Vec::from(body.as_bytes())));
     }
 
     pub fn set_bytes_body(&mut self, content_type: &str, body: &[u8]) {
+        self.set_body(content_type, HttpBody::Content(Vec::from(body)));
+    }
+
+    /// Sets a body that is read from `reader` while the response is written and
+    /// sent with chunked transfer-encoding.
+    pub fn set_stream_body(&mut self, content_type: &str, reader: impl Read + Send + 'static) {
+        self.set_body(content_type, HttpBody::Stream(Box::new(reader)));
+    }
+
+    /// Sets a body that is copied from the first `len` bytes of `file` while
+    /// the response is written.
+    pub fn set_file_body(&mut self, content_type: &str, file: File, len: u64) {
+        self.set_body(content_type, HttpBody::File { file, len });
+    }
+
+    /// Sets the body and the headers that describe it. Buffered bodies get a
+    /// Content-Length, streamed bodies are sent with chunked transfer-encoding.
+    pub fn set_body(&mut self, content_type: &str, body: HttpBody) {
         self.headers.set("Content-Type", content_type);
-        self.headers.set_content_length(body.len());
-        self.body = HttpBody::Content(Vec::from(body));
+        self.body = body;
+        self.set_framing_headers();
+    }
+
+    #[must_use]
+    pub const fn status(&self) -> &HttpStatus {
+        &self.status
     }
 
     pub fn set_header(&mut self, name: &str, value: &str) {
@@ -50,55 +127,194 @@ impl Response {
         self.headers.set(HEADER_CONTENT_ENCODING, encoding);
     }
 
+    /// Adds `name` to the Vary header unless it is already listed.
+    pub fn add_vary(&mut self, name: &str) {
+        let listed = self.headers.get_all(HEADER_VARY).iter().any(|value| {
+            value
+                .split(',')
+                .any(|v| v.trim() == "*" || v.trim().eq_ignore_ascii_case(name))
+        });
+
+        if !listed {
+            self.headers.add(HEADER_VARY, name);
+        }
+    }
+
+    /// Compresses the body with the best content coding the request's
+    /// Accept-Encoding allows. Any response with a body gets
+    /// `Vary: Accept-Encoding`, since another client may get another coding.
+    pub fn negotiate_encoding(
+        &mut self,
+        request_headers: &Headers,
+        level: CompressionLevel,
+    ) -> Result<()> {
+        if matches!(self.body, HttpBody::Empty) || !self.compressible {
+            return Ok(());
+        }
+
+        self.add_vary(HEADER_ACCEPT_ENCODING);
+        if let Some(coding) = request_headers.preferred_encoding(CONTENT_CODINGS) {
+            self.compress_with(coding, level)?;
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the body with one of the `CONTENT_CODINGS` at the default
+    /// level.
     pub fn compress(&mut self, encoding: &str) -> Result<()> {
-        if encoding == "gzip" && let HttpBody::Content(bytes) = &self.body {
-            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-            encoder.write_all(bytes)?;
-            let compressed_bytes = encoder.finish()?;
-            self.set_encoding("gzip");
-            self.body = HttpBody::Content(compressed_bytes);
-            self.headers.set_content_length(self.body.len());
+        self.compress_with(encoding, CompressionLevel::Default)
+    }
+
+    /// Encodes the body with one of the `CONTENT_CODINGS`. Unknown codings,
+    /// partial content and bodies that are already encoded are left as is.
+    pub fn compress_with(&mut self, encoding: &str, level: CompressionLevel) -> Result<()> {
+        // Content-Range positions refer to the uncompressed representation,
+        // both in the header and in the parts of a multipart/byteranges body
+        if !CONTENT_CODINGS.contains(&encoding)
+            || self.status == HttpStatus::PartialContent
+            || self.headers.get(HEADER_CONTENT_RANGE).is_some()
+            || self.headers.get(HEADER_CONTENT_ENCODING).is_some()
+        {
+            return Ok(());
+        }
+
+        match std::mem::replace(&mut self.body, HttpBody::Empty) {
+            HttpBody::Content(bytes) => {
+                let mut compressed = Vec::new();
+                encoder(encoding, level, Box::new(Cursor::new(bytes)))?.read_to_end(&mut compressed)?;
+                self.body = HttpBody::Content(compressed);
+            }
+            HttpBody::Stream(reader) => {
+                // compress while streaming so the body is never held in memory
+                self.body = HttpBody::Stream(encoder(encoding, level, reader)?);
+            }
+            HttpBody::File { file, len } => {
+                self.body = HttpBody::Stream(encoder(encoding, level, Box::new(file.take(len)))?);
+            }
+            HttpBody::Empty => return Ok(()),
         }
+
+        self.set_encoding(encoding);
+        self.set_framing_headers();
         Ok(())
     }
 
-    pub fn write(&self, stream: &mut impl Write) -> Result<()> {
+    /// Writes the response to the stream, consuming it since a streamed body
+    /// can only be read once.
+    pub fn write(mut self, stream: &mut impl Write) -> Result<()> {
         self.status.write_status_line(stream)?;
 
-        // Set Content-Length: 0 for empty body responses
-        if matches!(self.body, HttpBody::Empty) {
-            let mut headers = self.headers.clone();
-            headers.set_content_length(0);
-            headers.write(stream)?;
-        } else {
-            self.headers.write(stream)?;
+        // Set Content-Length: 0 for empty body responses. A 304 describes the
+        // representation the client already has, so it must not claim zero,
+        // and 1xx and 204 responses must not carry the field at all.
+        let has_no_length =
+            self.status.is_informational() || matches!(self.status.code(), 204 | 304);
+        if matches!(self.body, HttpBody::Empty) && !has_no_length {
+            self.headers.set_content_length(0);
         }
+        self.headers.write(stream)?;
 
         // empty line to separate body from headers
         stream.write_all(CRLF)?;
-        if let HttpBody::Content(body) = &self.body {
-            stream.write_all(body.as_slice())?;
+        if self.omit_body {
+            stream.flush()?;
+            return Ok(());
+        }
+
+        match self.body {
+            HttpBody::Empty => {}
+            HttpBody::Content(body) => stream.write_all(body.as_slice())?,
+            HttpBody::Stream(mut reader) => write_chunked(&mut reader, stream)?,
+            HttpBody::File { file, len } => {
+                let copied = std::io::copy(&mut file.take(len), stream)?;
+                if copied != len {
+                    // the file shrank after its length was sent; the client
+                    // can't tell where the body ends, so fail the connection
+                    anyhow::bail!("file ended after {copied} of {len} bytes");
+                }
+            }
         }
 
         stream.flush()?;
         Ok(())
     }
+
+    fn set_framing_headers(&mut self) {
+        if let Some(len) = self.body.len() {
+            self.headers.remove(HEADER_TRANSFER_ENCODING);
+            self.headers.set_content_length(len);
+        } else {
+            self.headers.remove(HEADER_CONTENT_LENGTH);
+            self.headers.set(HEADER_TRANSFER_ENCODING, "chunked");
+        }
+    }
+}
+
+/// Wraps `reader` so reading yields its content encoded with `coding`.
+fn encoder(
+    coding: &str,
+    level: CompressionLevel,
+    reader: Box<dyn Read + Send>,
+) -> Result<Box<dyn Read + Send>> {
+    Ok(match coding {
+        "br" => Box::new(brotli::CompressorReader::new(
+            reader,
+            BROTLI_BUFFER_SIZE,
+            level.brotli(),
+            BROTLI_WINDOW,
+        )),
+        "zstd" => Box::new(zstd::stream::read::Encoder::new(reader, level.zstd())?),
+        "gzip" => Box::new(flate2::read::GzEncoder::new(reader, level.flate())),
+        // the deflate coding is a zlib stream, not raw deflate
+        "deflate" => Box::new(flate2::read::ZlibEncoder::new(reader, level.flate())),
+        _ => anyhow::bail!("unsupported content coding {coding}"),
+    })
+}
+
+/// Copies everything from `reader` to `stream` using the chunked
+/// transfer-coding, followed by the terminating zero-length chunk.
+fn write_chunked(reader: &mut impl Read, stream: &mut impl Write) -> Result<()> {
+    let mut buffer = vec![0; STREAM_CHUNK_SIZE];
+
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        stream.write_all(format!("{n:x}").as_bytes())?;
+        stream.write_all(CRLF)?;
+        stream.write_all(&buffer[..n])?;
+        stream.write_all(CRLF)?;
+    }
+
+    stream.write_all(b"0")?;
+    stream.write_all(CRLF)?;
+    stream.write_all(CRLF)?;
+    Ok(())
 }
 
+#[must_use]
 pub fn bad_request(body: &str) -> Response {
     let mut resp = Response::new(HttpStatus::BadRequest);
     resp.set_str_body(body);
     resp
 }
 
+#[must_use]
 pub fn ok() -> Response {
     Response::new(HttpStatus::Ok)
 }
 
+#[must_use]
 pub fn not_found() -> Response {
     Response::new(HttpStatus::NotFound)
 }
 
+#[must_use]
 pub fn internal_server_error(message: Option<&str>) -> Response {
     let mut resp = Response::new(HttpStatus::InternalServerError);
     if let Some(msg) = message {
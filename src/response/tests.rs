@@ -383,3 +383,455 @@ fn test_gzip_compression() {
         output.contains(&format!("content-length: {expected_len}\r\n"))
     );
 }
+
+// Tests for streamed bodies
+
+/// Reassembles the data of a chunked body.
+fn dechunk(mut remaining: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    loop {
+        let line_end = remaining.windows(2).position(|w| w == b"\r\n").unwrap();
+        let size = std::str::from_utf8(&remaining[..line_end]).unwrap();
+        let size = usize::from_str_radix(size, 16).unwrap();
+        if size == 0 {
+            return data;
+        }
+        data.extend_from_slice(&remaining[line_end + 2..line_end + 2 + size]);
+        remaining = &remaining[line_end + 2 + size + 2..];
+    }
+}
+
+#[test]
+fn test_stream_body_is_written_chunked() {
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_stream_body("text/plain", std::io::Cursor::new(b"Hello, World!".to_vec()));
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+
+    assert!(output.contains("transfer-encoding: chunked\r\n"));
+    assert!(!output.contains("content-length"));
+    assert!(output.ends_with("\r\n\r\nd\r\nHello, World!\r\n0\r\n\r\n"));
+}
+
+#[test]
+fn test_stream_body_larger_than_chunk_size() {
+    let body = vec![b'x'; STREAM_CHUNK_SIZE + 10];
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_stream_body("application/octet-stream", std::io::Cursor::new(body));
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+
+    assert!(output.contains(&format!("\r\n{STREAM_CHUNK_SIZE:x}\r\n")));
+    assert!(output.ends_with(&format!("\r\na\r\n{}\r\n0\r\n\r\n", "x".repeat(10))));
+}
+
+#[test]
+fn test_body_from_chunks() {
+    let chunks = vec![b"Hello".to_vec(), Vec::new(), b", ".to_vec(), b"World".to_vec()];
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_body("text/plain", HttpBody::from_chunks(chunks.into_iter()));
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+
+    let body_start = output.find("\r\n\r\n").unwrap() + 4;
+    let decoded = dechunk(&output.as_bytes()[body_start..]);
+    assert_eq!(decoded, b"Hello, World");
+}
+
+#[test]
+fn test_set_bytes_body_after_stream_restores_content_length() {
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_stream_body("text/plain", std::io::Cursor::new(b"streamed".to_vec()));
+    resp.set_bytes_body("text/plain", b"buffered");
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+
+    assert!(output.contains("content-length: 8\r\n"));
+    assert!(!output.contains("transfer-encoding"));
+    assert!(output.ends_with("\r\n\r\nbuffered"));
+}
+
+#[test]
+fn test_gzip_compression_of_stream_body() {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    let original_body = "streamed content that gets compressed on the fly";
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_stream_body("text/plain", std::io::Cursor::new(original_body.as_bytes().to_vec()));
+    resp.compress("gzip").unwrap();
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+
+    let body_start = buffer.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    let head = String::from_utf8_lossy(&buffer[..body_start]);
+    assert!(head.contains("content-encoding: gzip\r\n"));
+    assert!(head.contains("transfer-encoding: chunked\r\n"));
+
+    let compressed = dechunk(&buffer[body_start..]);
+
+    let mut decompressed = String::new();
+    GzDecoder::new(compressed.as_slice())
+        .read_to_string(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, original_body);
+}
+
+// Tests for file bodies
+
+/// Creates a file with the given content in the temp directory.
+fn temp_file(name: &str, content: &[u8]) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("response-test-{}-{name}", std::process::id()));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn test_file_body_sets_content_length() {
+    let path = temp_file("length", b"file content");
+    let file = std::fs::File::open(&path).unwrap();
+
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_file_body("application/octet-stream", file, 12);
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert!(output.contains("content-length: 12\r\n"));
+    assert!(!output.contains("transfer-encoding"));
+    assert!(output.ends_with("\r\n\r\nfile content"));
+}
+
+#[test]
+fn test_file_body_only_sends_len_bytes() {
+    let path = temp_file("partial", b"file content");
+    let file = std::fs::File::open(&path).unwrap();
+
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_file_body("application/octet-stream", file, 4);
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert!(output.ends_with("\r\n\r\nfile"));
+}
+
+#[test]
+fn test_file_body_shorter_than_len_fails() {
+    let path = temp_file("short", b"tiny");
+    let file = std::fs::File::open(&path).unwrap();
+
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_file_body("application/octet-stream", file, 100);
+
+    let mut buffer = Vec::new();
+    let result = resp.write(&mut buffer);
+    std::fs::remove_file(path).unwrap();
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_gzip_compression_of_file_body() {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    let original_body = b"file content that gets compressed while streaming";
+    let path = temp_file("gzip", original_body);
+    let file = std::fs::File::open(&path).unwrap();
+
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_file_body("text/plain", file, original_body.len() as u64);
+    resp.compress("gzip").unwrap();
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let body_start = buffer.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    let head = String::from_utf8_lossy(&buffer[..body_start]);
+    assert!(head.contains("transfer-encoding: chunked\r\n"));
+    assert!(!head.contains("content-length"));
+
+    let mut decompressed = Vec::new();
+    GzDecoder::new(dechunk(&buffer[body_start..]).as_slice())
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, original_body);
+}
+
+#[test]
+fn test_compress_skips_partial_content() {
+    let mut resp = Response::new(HttpStatus::PartialContent);
+    resp.set_header("Content-Range", "bytes 0-4/100");
+    resp.set_str_body("Hello");
+    resp.compress("gzip").unwrap();
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+
+    assert!(!output.contains("content-encoding"));
+    assert!(output.ends_with("\r\n\r\nHello"));
+}
+
+#[test]
+fn test_compress_skips_multipart_partial_content() {
+    // a multipart 206 carries its ranges in the body, not in Content-Range
+    let mut resp = Response::new(HttpStatus::PartialContent);
+    let body = b"--b\r\n\r\nHello\r\n--b--".to_vec();
+    resp.set_body("multipart/byteranges; boundary=b", HttpBody::Content(body));
+    resp.compress("gzip").unwrap();
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+
+    assert!(!output.contains("content-encoding"));
+    assert!(output.ends_with("\r\n\r\n--b\r\n\r\nHello\r\n--b--"));
+}
+
+#[test]
+fn test_not_modified_has_no_content_length() {
+    let resp = Response::new(HttpStatus::NotModified);
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+
+    assert!(output.starts_with("HTTP/1.1 304 Not Modified\r\n"));
+    assert!(!output.contains("content-length"));
+}
+
+#[test]
+fn test_no_content_and_informational_have_no_content_length() {
+    for status in [HttpStatus::NoContent, HttpStatus::Continue] {
+        let resp = Response::new(status);
+
+        let mut buffer = Vec::new();
+        resp.write(&mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+
+        assert!(!output.contains("content-length"), "{output}");
+    }
+}
+
+fn compressed_body(coding: &str, body: &str) -> (String, Vec<u8>) {
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_str_body(body);
+    resp.compress(coding).unwrap();
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let body_start = buffer.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    let head = String::from_utf8_lossy(&buffer[..body_start]).into_owned();
+    (head, buffer[body_start..].to_vec())
+}
+
+#[test]
+fn test_brotli_compression() {
+    use std::io::Read;
+
+    let original_body = "brotli brotli brotli brotli brotli";
+    let (head, body) = compressed_body("br", original_body);
+    assert!(head.contains("content-encoding: br\r\n"));
+    assert!(head.contains(&format!("content-length: {}\r\n", body.len())));
+
+    let mut decompressed = String::new();
+    brotli::Decompressor::new(body.as_slice(), 4096)
+        .read_to_string(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, original_body);
+}
+
+#[test]
+fn test_zstd_compression() {
+    let original_body = "zstd zstd zstd zstd zstd zstd zstd";
+    let (head, body) = compressed_body("zstd", original_body);
+    assert!(head.contains("content-encoding: zstd\r\n"));
+
+    let decompressed = zstd::decode_all(body.as_slice()).unwrap();
+    assert_eq!(decompressed, original_body.as_bytes());
+}
+
+#[test]
+fn test_deflate_compression_uses_zlib_format() {
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    let original_body = "deflate deflate deflate deflate";
+    let (head, body) = compressed_body("deflate", original_body);
+    assert!(head.contains("content-encoding: deflate\r\n"));
+
+    let mut decompressed = String::new();
+    ZlibDecoder::new(body.as_slice())
+        .read_to_string(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, original_body);
+}
+
+#[test]
+fn test_compress_ignores_unknown_coding_and_encoded_bodies() {
+    let (head, body) = compressed_body("compress", "Hello");
+    assert!(!head.contains("content-encoding"));
+    assert_eq!(body, b"Hello");
+
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_bytes_body("text/plain", b"already gzipped");
+    resp.set_encoding("gzip");
+    resp.compress("br").unwrap();
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+    assert!(output.contains("content-encoding: gzip\r\n"));
+    assert!(output.ends_with("\r\n\r\nalready gzipped"));
+}
+
+fn negotiated(accept_encoding: Option<&str>, body: Option<&str>) -> String {
+    let mut request_headers = Headers::new();
+    if let Some(value) = accept_encoding {
+        request_headers.add("Accept-Encoding", value);
+    }
+
+    let mut resp = Response::new(HttpStatus::Ok);
+    if let Some(body) = body {
+        resp.set_str_body(body);
+    }
+    resp.negotiate_encoding(&request_headers, CompressionLevel::Default)
+        .unwrap();
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let body_start = buffer.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    String::from_utf8_lossy(&buffer[..body_start]).into_owned()
+}
+
+#[test]
+fn test_negotiate_encoding_picks_best_coding_and_sets_vary() {
+    let head = negotiated(Some("gzip;q=0.5, br"), Some("Hello"));
+    assert!(head.contains("content-encoding: br\r\n"));
+    assert!(head.contains("vary: Accept-Encoding\r\n"));
+
+    let head = negotiated(Some("gzip;q=0, br;q=0"), Some("Hello"));
+    assert!(!head.contains("content-encoding"));
+    assert!(head.contains("vary: Accept-Encoding\r\n"));
+
+    let head = negotiated(None, Some("Hello"));
+    assert!(!head.contains("content-encoding"));
+    assert!(head.contains("vary: Accept-Encoding\r\n"));
+}
+
+#[test]
+fn test_negotiate_encoding_skips_empty_body() {
+    let head = negotiated(Some("gzip"), None);
+    assert!(!head.contains("content-encoding"));
+    assert!(!head.contains("vary"));
+}
+
+#[test]
+fn test_add_vary_appends_once() {
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_header("Vary", "Origin");
+    resp.add_vary("Accept-Encoding");
+    resp.add_vary("accept-encoding");
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+    assert!(output.contains("vary: Origin, Accept-Encoding\r\n"));
+}
+
+#[test]
+fn test_negotiate_encoding_skips_disabled_compression() {
+    let mut request_headers = Headers::new();
+    request_headers.add("Accept-Encoding", "gzip");
+
+    let mut resp = Response::new(HttpStatus::Ok);
+    resp.set_str_body("Hello");
+    resp.disable_compression();
+    resp.negotiate_encoding(&request_headers, CompressionLevel::Default)
+        .unwrap();
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+    assert!(!output.contains("content-encoding"));
+    assert!(output.ends_with("\r\n\r\nHello"));
+}
+
+// Tests for Response::omit_body()
+#[test]
+fn test_omit_body_keeps_headers_of_content_body() {
+    let mut resp = ok();
+    resp.set_str_body("Hello, World!");
+    resp.omit_body();
+
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+
+    let output = String::from_utf8(buffer).unwrap();
+    assert!(output.contains("content-length: 13\r\n"));
+    assert!(output.contains("content-type: text/plain\r\n"));
+    assert!(output.ends_with("\r\n\r\n"));
+}
+
+#[test]
+fn test_omit_body_keeps_compressed_length() {
+    let body = "a".repeat(1000);
+    let mut full = ok();
+    full.set_str_body(&body);
+    full.compress("gzip").unwrap();
+    let compressed_len = full.body_len().unwrap();
+
+    let mut resp = ok();
+    resp.set_str_body(&body);
+    resp.compress("gzip").unwrap();
+    resp.omit_body();
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+
+    let output = String::from_utf8(buffer).unwrap();
+    assert!(output.contains(&format!("content-length: {compressed_len}\r\n")));
+    assert!(output.contains("content-encoding: gzip\r\n"));
+    assert!(output.ends_with("\r\n\r\n"));
+}
+
+#[test]
+fn test_omit_body_skips_stream_and_file_bodies() {
+    let mut resp = ok();
+    resp.set_stream_body("text/plain", Cursor::new(b"streamed".to_vec()));
+    resp.omit_body();
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+
+    let output = String::from_utf8(buffer).unwrap();
+    assert!(output.contains("transfer-encoding: chunked\r\n"));
+    assert!(output.ends_with("\r\n\r\n"));
+
+    let path = std::env::temp_dir().join(format!("response-test-{}-omit", std::process::id()));
+    std::fs::write(&path, b"file content").unwrap();
+    let mut resp = ok();
+    resp.set_file_body("text/plain", File::open(&path).unwrap(), 12);
+    resp.omit_body();
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let output = String::from_utf8(buffer).unwrap();
+    assert!(output.contains("content-length: 12\r\n"));
+    assert!(output.ends_with("\r\n\r\n"));
+}
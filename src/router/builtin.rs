@@ -0,0 +1,44 @@
+use anyhow::Result;
+
+use super::{Handler, State};
+use crate::consts;
+use crate::{request::Request, response, response::Response};
+
+/// Answers `/` with an empty 200 OK.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Index;
+
+impl Handler for Index {
+    fn handle(&self, _req: &Request, _state: &State) -> Result<Response> {
+        Ok(response::ok())
+    }
+}
+
+/// Answers with the `message` parameter of the route as a plain text body.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Echo;
+
+impl Handler for Echo {
+    fn handle(&self, req: &Request, _state: &State) -> Result<Response> {
+        let mut resp = response::ok();
+        resp.set_str_body(req.param("message").unwrap_or_default());
+        Ok(resp)
+    }
+}
+
+/// Answers with the value of the request's User-Agent header.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserAgent;
+
+impl Handler for UserAgent {
+    fn handle(&self, req: &Request, _state: &State) -> Result<Response> {
+        if let Some(value) = req.headers().get(consts::HEADER_USER_AGENT) {
+            let mut resp = response::ok();
+            resp.set_str_body(value);
+
+            return Ok(resp);
+        }
+
+        Ok(response::bad_request("missing user-agent header"))
+    }
+}
@@ -0,0 +1,412 @@
+use crate::body::HttpBody;
+use crate::compression::CompressionPolicy;
+use crate::conditional::{self, Precondition, Validators};
+use crate::consts;
+use crate::file;
+use crate::header;
+use crate::http::status::HttpStatus;
+use crate::http::uri;
+use crate::range;
+use crate::response::Response;
+use crate::{request::Request, response};
+
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+
+/// Settings for how the router serves files under /files.
+#[derive(Debug, Clone, Default)]
+pub struct FileOptions {
+    pub mime_types: file::MimeRegistry,
+    /// Render a listing when a path points at a directory.
+    pub autoindex: bool,
+    /// A file served in place of a directory when the directory contains it.
+    pub index_file: Option<String>,
+    /// Precompressed siblings are only served where this policy allows a
+    /// content coding.
+    pub compression: CompressionPolicy,
+}
+
+/// The handlers of the /files routes. The file path relative to the served
+/// directory is taken from the `path` parameter of the route.
+pub struct FileRoutes {
+    file_server: Box<dyn file::FileSystem + Send + Sync>,
+    options: FileOptions,
+}
+
+impl FileRoutes {
+    #[must_use]
+    pub fn new(file_server: Box<dyn file::FileSystem + Send + Sync>, options: FileOptions) -> Self {
+        Self {
+            file_server,
+            options,
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, req: &Request) -> Response {
+        self.serve_file(req, req.param("path").unwrap_or_default())
+    }
+
+    /// Creates or replaces a file with the request body, which must be sent
+    /// as application/octet-stream.
+    pub fn post(&self, req: &Request) -> Response {
+        if req
+            .headers()
+            .content_type()
+            .is_none_or(|v| v != "application/octet-stream")
+        {
+            return response::Response::new(HttpStatus::BadRequest);
+        }
+
+        // request bodies are always buffered by the parser
+        match req.body() {
+            HttpBody::Empty | HttpBody::Stream(_) | HttpBody::File { .. } => {
+                response::Response::new(HttpStatus::NoContent)
+            }
+            HttpBody::Content(data) => self
+                .file_server
+                .save(req.param("path").unwrap_or_default(), data)
+                .map_or_else(saver_error_response, |()| {
+                    response::Response::new(HttpStatus::Created)
+                }),
+        }
+    }
+
+    #[must_use]
+    pub fn put(&self, req: &Request) -> Response {
+        self.put_file(req, req.param("path").unwrap_or_default())
+    }
+
+    #[must_use]
+    pub fn patch(&self, req: &Request) -> Response {
+        self.patch_file(req, req.param("path").unwrap_or_default())
+    }
+
+    pub fn delete(&self, req: &Request) -> Response {
+        let path = req.param("path").unwrap_or_default();
+        if let Err(resp) = self.check_preconditions(req, path) {
+            return resp;
+        }
+
+        self.file_server
+            .delete(path)
+            .map_or_else(saver_error_response, |()| {
+                response::Response::new(HttpStatus::NoContent)
+            })
+    }
+
+    /// Responds with the content of a file, honoring conditional and Range
+    /// requests. If the client accepts a content coding the file has a
+    /// precompressed sibling for, the sibling is sent instead.
+    fn serve_file(&self, req: &Request, path: &str) -> Response {
+        let f = match self.file_server.open(path) {
+            Ok(f) => f,
+            Err(file::FileRetrieverError::IsDirectory) => {
+                return self.serve_directory(req, path);
+            }
+            Err(e) => return retriever_error_response(e),
+        };
+        let mut file = f.file;
+        let content_type = self.options.mime_types.detect(path, &mut file);
+
+        // only siblings in a coding the client accepts are worth opening
+        let precompressed = self.options.compression.allows_path(req.path());
+        let acceptable = file::PRECOMPRESSED_EXTENSIONS
+            .iter()
+            .map(|(coding, _)| *coding)
+            .filter(|coding| {
+                precompressed && req.headers().preferred_encoding(&[coding]).is_some()
+            });
+
+        // a sibling older than the file was built from a previous version
+        let mut siblings = acceptable
+            .filter_map(|coding| {
+                let sibling = self.file_server.open_precompressed(path, coding).ok()?;
+                let stale = f
+                    .metadata
+                    .modified
+                    .zip(sibling.metadata.modified)
+                    .is_some_and(|(original, modified)| modified < original);
+                (!stale).then_some((coding, sibling))
+            })
+            .collect::<Vec<_>>();
+        let codings = siblings.iter().map(|(coding, _)| *coding).collect::<Vec<&str>>();
+        let encoding = req.headers().preferred_encoding(&codings);
+
+        let (file, metadata) = match encoding {
+            Some(coding) => {
+                let index = codings.iter().position(|c| *c == coding).unwrap_or_default();
+                let (_, sibling) = siblings.swap_remove(index);
+                (sibling.file, sibling.metadata)
+            }
+            None => (file, f.metadata),
+        };
+        let total = metadata.len;
+        let validators = metadata.validators();
+
+        let mut resp = match conditional::evaluate(*req.method(), req.headers(), &validators) {
+            Precondition::Proceed => {
+                let mut resp = Self::file_response(req, file, &content_type, total, &validators);
+                // an error response such as a 416 has no encoded body
+                if let Some(coding) = encoding
+                    && matches!(resp.status().code(), 200 | 206)
+                {
+                    resp.set_encoding(coding);
+                }
+                resp
+            }
+            Precondition::NotModified => response::Response::new(HttpStatus::NotModified),
+            Precondition::Failed => return response::Response::new(HttpStatus::PreconditionFailed),
+        };
+
+        // whether a sibling exists for a coding the client doesn't accept
+        // isn't checked, so any response here may vary
+        if precompressed {
+            resp.add_vary(consts::HEADER_ACCEPT_ENCODING);
+        }
+        if let Some(etag) = &validators.etag {
+            resp.set_header(consts::HEADER_ETAG, &etag.to_string());
+        }
+        if let Some(modified) = validators.last_modified {
+            resp.set_header(consts::HEADER_LAST_MODIFIED, &conditional::http_date(modified));
+        }
+
+        resp
+    }
+
+    /// Creates or replaces a file with the request body. Responds with 201 if
+    /// the file was created and 204 if it was replaced.
+    fn put_file(&self, req: &Request, path: &str) -> Response {
+        let existing = match self.check_preconditions(req, path) {
+            Ok(existing) => existing,
+            Err(resp) => return resp,
+        };
+
+        let data = match req.body() {
+            HttpBody::Content(data) => data.as_slice(),
+            _ => &[],
+        };
+
+        self.file_server.save(path, data).map_or_else(saver_error_response, |()| {
+            if existing.is_some() {
+                response::Response::new(HttpStatus::NoContent)
+            } else {
+                response::Response::new(HttpStatus::Created)
+            }
+        })
+    }
+
+    /// Evaluates the conditional headers of a request that modifies the file
+    /// at `path`, so If-Match and If-None-Match: * let clients avoid lost
+    /// updates. Returns the validators of the file if it exists, or the
+    /// response to send instead of modifying it.
+    fn check_preconditions(
+        &self,
+        req: &Request,
+        path: &str,
+    ) -> Result<Option<Validators>, Response> {
+        let existing = match self.file_server.open(path) {
+            Ok(f) => Some(f.metadata.validators()),
+            Err(file::FileRetrieverError::NotFound) => None,
+            Err(e) => return Err(retriever_error_response(e)),
+        };
+
+        let precondition = existing.as_ref().map_or_else(
+            || {
+                if req.headers().get(consts::HEADER_IF_MATCH).is_some() {
+                    Precondition::Failed
+                } else {
+                    Precondition::Proceed
+                }
+            },
+            |validators| conditional::evaluate(*req.method(), req.headers(), validators),
+        );
+        if precondition != Precondition::Proceed {
+            return Err(response::Response::new(HttpStatus::PreconditionFailed));
+        }
+
+        Ok(existing)
+    }
+
+    /// Writes the request body into an existing file. The body is written at
+    /// the position given by a `Content-Range: bytes start-end/*` header, or
+    /// appended if there is none.
+    fn patch_file(&self, req: &Request, path: &str) -> Response {
+        if let Err(resp) = self.check_preconditions(req, path) {
+            return resp;
+        }
+
+        let data = match req.body() {
+            HttpBody::Content(data) => data.as_slice(),
+            _ => &[],
+        };
+
+        let offset = match req.headers().get(consts::HEADER_CONTENT_RANGE) {
+            None => None,
+            Some(value) => match range::parse_content_range(value) {
+                Some(r) if r.len() == data.len() as u64 => Some(r.start),
+                _ => return response::bad_request("invalid Content-Range"),
+            },
+        };
+
+        self.file_server
+            .write_at(path, offset, data)
+            .map_or_else(saver_error_response, |()| {
+                response::Response::new(HttpStatus::NoContent)
+            })
+    }
+
+    /// Responds to a request for a directory with its index file if there is
+    /// one, or with a listing if autoindex is enabled.
+    fn serve_directory(&self, req: &Request, path: &str) -> Response {
+        if let Some(index) = &self.options.index_file {
+            let index_path = format!("{}/{index}", path.trim_end_matches('/'));
+            let index_path = index_path.trim_start_matches('/');
+            if self.file_server.open(index_path).is_ok() {
+                // relative links in the index resolve against the directory
+                // only if its URL ends with a slash
+                if !req.raw_path().ends_with('/') {
+                    return redirect_to_directory(req);
+                }
+                return self.serve_file(req, index_path);
+            }
+        }
+
+        if !self.options.autoindex {
+            return response::Response::new(HttpStatus::Forbidden);
+        }
+
+        let entries = match self.file_server.list(path) {
+            Ok(entries) => entries,
+            Err(e) => return retriever_error_response(e),
+        };
+
+        let base = format!("/files/{}", uri::encode_path(path.trim_end_matches('/')));
+        let mut resp = response::ok();
+        // the format of the listing depends on Accept
+        resp.add_vary(consts::HEADER_ACCEPT);
+        if prefers_json(req.headers().accept()) {
+            resp.set_bytes_body("application/json", file::render_json(&base, &entries).as_bytes());
+        } else {
+            resp.set_bytes_body("text/html", file::render_html(&base, &entries).as_bytes());
+        }
+
+        resp
+    }
+
+    /// Builds the response carrying the file's content, honoring Range requests.
+    fn file_response(
+        req: &Request,
+        mut file: File,
+        content_type: &str,
+        total: u64,
+        validators: &Validators,
+    ) -> Response {
+        // a stale If-Range means the client's partial copy is outdated, so it
+        // gets the full file instead
+        let ranges = req
+            .headers()
+            .range()
+            .filter(|_| {
+                req.headers()
+                    .if_range()
+                    .is_none_or(|v| range::if_range_matches(v, validators))
+            })
+            .map(|v| range::parse(v, total));
+
+        let mut resp = match ranges {
+            Some(Ok(ranges)) if ranges.len() == 1 => {
+                let r = ranges[0];
+                if let Err(e) = file.seek(SeekFrom::Start(r.start)) {
+                    return response::internal_server_error(Some(&e.to_string()));
+                }
+
+                let mut resp = response::Response::new(HttpStatus::PartialContent);
+                resp.set_header(consts::HEADER_CONTENT_RANGE, &r.content_range(total));
+                resp.set_file_body(content_type, file, r.len());
+                resp
+            }
+            Some(Ok(ranges)) => {
+                let boundary = range::boundary();
+                let body = range::multipart_body(file, &ranges, total, content_type, &boundary);
+
+                let mut resp = response::Response::new(HttpStatus::PartialContent);
+                resp.set_stream_body(&format!("multipart/byteranges; boundary={boundary}"), body);
+                resp
+            }
+            Some(Err(range::RangeError::Unsatisfiable)) => {
+                let mut resp = response::Response::new(HttpStatus::RangeNotSatisfiable);
+                resp.set_header(
+                    consts::HEADER_CONTENT_RANGE,
+                    &range::unsatisfied_content_range(total),
+                );
+                resp
+            }
+            None | Some(Err(range::RangeError::Invalid)) => {
+                let mut resp = response::ok();
+                resp.set_file_body(content_type, file, total);
+                resp
+            }
+        };
+
+        resp.set_header(consts::HEADER_ACCEPT_RANGES, "bytes");
+        resp
+    }
+}
+
+/// Redirects a request for a directory to the same path with a trailing
+/// slash, keeping the query.
+fn redirect_to_directory(req: &Request) -> Response {
+    let mut location = format!("{}/", req.raw_path());
+    if let Some(query) = req.query_string() {
+        location.push('?');
+        location.push_str(query);
+    }
+
+    let mut resp = response::Response::new(HttpStatus::MovedPermanently);
+    resp.set_header(consts::HEADER_LOCATION, &location);
+    resp
+}
+
+/// Returns true if an Accept value ranks JSON above HTML. Each media type
+/// gets the quality of the most specific range that matches it, and HTML wins
+/// ties since it's what a listing is rendered as by default.
+fn prefers_json(accept: Option<&str>) -> bool {
+    let items = accept.map(header::parse_quality_list).unwrap_or_default();
+    let quality_of = |media_type: &str| {
+        let top = media_type.split('/').next().unwrap_or_default();
+        let ranges = [media_type, &format!("{top}/*"), "*/*"];
+        ranges
+            .iter()
+            .find_map(|range| items.iter().find(|item| item.value == *range))
+            .map_or(0, |item| item.quality)
+    };
+
+    quality_of("application/json") > quality_of("text/html")
+}
+
+fn retriever_error_response(e: file::FileRetrieverError) -> Response {
+    match e {
+        file::FileRetrieverError::NotFound => response::not_found(),
+        file::FileRetrieverError::IsDirectory => response::bad_request("path is a directory"),
+        file::FileRetrieverError::InvalidPath(msg) => response::bad_request(&msg),
+        file::FileRetrieverError::Forbidden => response::Response::new(HttpStatus::Forbidden),
+        file::FileRetrieverError::Other(msg) => response::internal_server_error(Some(&msg)),
+    }
+}
+
+fn saver_error_response(e: file::FileSaverError) -> Response {
+    match e {
+        file::FileSaverError::InvalidPath(msg) => response::bad_request(&msg),
+        file::FileSaverError::Forbidden => response::Response::new(HttpStatus::Forbidden),
+        file::FileSaverError::NotFound => response::not_found(),
+        file::FileSaverError::InvalidOffset(_) => {
+            response::Response::new(HttpStatus::RangeNotSatisfiable)
+        }
+        file::FileSaverError::MissingParent(_) => response::Response::new(HttpStatus::Conflict),
+        file::FileSaverError::CreateDirectory(msg)
+        | file::FileSaverError::Write(msg)
+        | file::FileSaverError::Commit(msg)
+        | file::FileSaverError::Other(msg) => response::internal_server_error(Some(&msg)),
+    }
+}
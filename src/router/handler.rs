@@ -0,0 +1,60 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::request::Request;
+use crate::response::Response;
+
+/// Produces the response to a request.
+///
+/// Handlers are shared by all worker threads. Data they need besides the
+/// request, such as a database pool, can be registered once on the server
+/// and is passed to every call in `state`. Closures taking a `&Request` and
+/// a `&State` are handlers too.
+pub trait Handler: Send + Sync {
+    fn handle(&self, req: &Request, state: &State) -> Result<Response>;
+}
+
+impl<F> Handler for F
+where
+    F: Fn(&Request, &State) -> Result<Response> + Send + Sync,
+{
+    fn handle(&self, req: &Request, state: &State) -> Result<Response> {
+        self(req, state)
+    }
+}
+
+/// Values shared by all handlers, stored and looked up by their type.
+#[derive(Default)]
+pub struct State {
+    values: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl State {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a value, replacing any value of the same type.
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) {
+        self.values.insert(TypeId::of::<T>(), Box::new(value));
+    }
+
+    /// Returns the value of type `T`, or None if none was stored.
+    #[must_use]
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+    }
+}
+
+impl std::fmt::Debug for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("State")
+            .field("values", &self.values.len())
+            .finish()
+    }
+}
@@ -1,104 +1,204 @@
-use crate::body::HttpBody;
+/*
+ * This module dispatches requests to handlers registered for a method and a
+ * path pattern.
+ */
+
+mod builtin;
+mod files;
+mod handler;
+mod pattern;
+
+#[cfg(test)]
+mod tests;
+
+pub use builtin::{Echo, Index, UserAgent};
+pub use files::{FileOptions, FileRoutes};
+pub use handler::{Handler, State};
+pub use pattern::Pattern;
+
+use std::sync::Arc;
+
 use crate::consts;
 use crate::file;
 use crate::http::method::HttpMethod;
 use crate::http::status::HttpStatus;
+use crate::http::uri;
 use crate::response::Response;
 use crate::{request::Request, response};
 
-use anyhow::{Ok, Result};
+use anyhow::Result;
 
+type FileHandler = fn(&FileRoutes, &Request) -> Response;
+
+struct Route {
+    method: HttpMethod,
+    pattern: Pattern,
+    handler: Box<dyn Handler>,
+}
+
+/// A routing table that dispatches to the first route whose method and
+/// pattern match.
+///
+/// If only the method mismatches the response is 405 Method Not Allowed with
+/// an `Allow` header, otherwise 404 Not Found.
+#[derive(Default)]
 pub struct Router {
-    file_server: Box<dyn file::FileSystem + Send + Sync>,
+    routes: Vec<Route>,
 }
 
 impl Router {
-    pub fn new(file_server: Box<dyn file::FileSystem + Send + Sync>) -> Self {
-        Self { file_server }
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    pub fn handle(&self, req: &Request) -> Result<Response> {
-        if req.path_match_exact("/") {
-            return Ok(response::ok());
-        }
+    /// Creates a router with all built-in routes: `/`, `/echo/*message`,
+    /// `/user-agent` and the /files routes.
+    pub fn standard(
+        file_server: Box<dyn file::FileSystem + Send + Sync>,
+        options: FileOptions,
+    ) -> Result<Self> {
+        Self::new()
+            .index()?
+            .echo()?
+            .user_agent()?
+            .files(FileRoutes::new(file_server, options))
+    }
+
+    /// Registers a handler for requests with the method whose path matches
+    /// the pattern.
+    pub fn route<H>(mut self, method: HttpMethod, pattern: &str, handler: H) -> Result<Self>
+    where
+        H: Handler + 'static,
+    {
+        self.routes.push(Route {
+            method,
+            pattern: Pattern::parse(pattern)?,
+            handler: Box::new(handler),
+        });
 
-        if req.path_match_prefix("/echo/") {
-            let message = &req.path()[6..];
-            let mut resp = response::ok();
-            resp.set_str_body(message);
-            return Ok(resp);
+        Ok(self)
+    }
+
+    /// Registers the built-in `GET /` route.
+    pub fn index(self) -> Result<Self> {
+        self.route(HttpMethod::Get, "/", Index)
+    }
+
+    /// Registers the built-in `GET /echo/*message` route.
+    pub fn echo(self) -> Result<Self> {
+        self.route(HttpMethod::Get, "/echo/*message", Echo)
+    }
+
+    /// Registers the built-in `GET /user-agent` route.
+    pub fn user_agent(self) -> Result<Self> {
+        self.route(HttpMethod::Get, "/user-agent", UserAgent)
+    }
+
+    /// Registers the built-in /files routes, which serve and modify the files
+    /// below the directory of `files`.
+    pub fn files(mut self, files: FileRoutes) -> Result<Self> {
+        let files = Arc::new(files);
+        let file_handlers: [(HttpMethod, FileHandler); 5] = [
+            (HttpMethod::Get, FileRoutes::get),
+            (HttpMethod::Post, FileRoutes::post),
+            (HttpMethod::Put, FileRoutes::put),
+            (HttpMethod::Patch, FileRoutes::patch),
+            (HttpMethod::Delete, FileRoutes::delete),
+        ];
+        for (method, handler) in file_handlers {
+            let files = Arc::clone(&files);
+            self = self.route(
+                method,
+                "/files/*path",
+                move |req: &Request, _state: &State| {
+                    // the file path can't tell an encoded '/' from a separator
+                    if uri::has_encoded_slash(req.raw_path()) {
+                        return Ok(response::bad_request("path contains an encoded '/'"));
+                    }
+                    Ok(handler(&files, req))
+                },
+            )?;
         }
 
-        if req.path_match_exact("/user-agent") {
-            if let Some(value) = req.headers().get(consts::HEADER_USER_AGENT) {
-                let mut resp = response::ok();
-                resp.set_str_body(value);
+        Ok(self)
+    }
 
-                return Ok(resp);
-            }
+    /// Dispatches the request to the matching route. The parameters captured
+    /// by the route's pattern are stored on the request.
+    ///
+    /// A HEAD request without a HEAD route of its own is handled by the GET
+    /// route, and the server leaves out the body of the response. An OPTIONS
+    /// request without an OPTIONS route is answered with the allowed methods.
+    pub fn handle(&self, req: &mut Request, state: &State) -> Result<Response> {
+        let method = *req.method();
+        let mut found = self.find(method, req.raw_path());
+        if found.is_none() && method == HttpMethod::Head {
+            found = self.find(HttpMethod::Get, req.raw_path());
+        }
+        if let Some((route, params)) = found {
+            req.set_params(params);
+            return route.handler.handle(req, state);
+        }
 
-            return Ok(response::bad_request("missing user-agent header"));
+        let allowed = self.allowed_methods(req.raw_path());
+        if allowed.is_empty() {
+            return Ok(response::not_found());
         }
 
-        if req.path_match_prefix("/files") {
-            return match req.method() {
-                HttpMethod::Get => {
-                    let path = &req.path()[7..];
-                    if path.contains("..") || path.starts_with('/') {
-                        return Ok(response::Response::new(HttpStatus::Forbidden));
-                    }
+        let allow = allowed
+            .iter()
+            .map(|method| method.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ");
+        // OPTIONS is answered for any path with routes, unless a route
+        // handles it explicitly
+        let status = if method == HttpMethod::Options {
+            HttpStatus::NoContent
+        } else {
+            HttpStatus::MethodNotAllowed
+        };
+        let mut resp = Response::new(status);
+        resp.set_header(consts::HEADER_ALLOW, &allow);
+        Ok(resp)
+    }
 
-                    return Ok(self.file_server.retrieve(path).map_or_else(
-                        |e| match e {
-                            file::FileRetrieverError::NotFound => {
-                                response::Response::new(HttpStatus::NotFound)
-                            }
-                            file::FileRetrieverError::Other(msg) => {
-                                response::internal_server_error(Some(&msg))
-                            }
-                        },
-                        |c| {
-                            let mut resp = response::ok();
-                            resp.set_bytes_body("application/octet-stream", &c);
-                            resp
-                        },
-                    ));
-                }
-                HttpMethod::Post => {
-                    if req
-                        .headers()
-                        .content_type()
-                        .is_none_or(|v| v != "application/octet-stream")
-                    {
-                        return Ok(response::Response::new(HttpStatus::BadRequest));
-                    }
+    /// Returns the methods of the routes whose pattern matches the path, in
+    /// registration order. HEAD follows GET and OPTIONS comes last, as both
+    /// are answered for every path with routes. The path `*` matches all
+    /// routes, as used by `OPTIONS *`.
+    fn allowed_methods(&self, path: &str) -> Vec<HttpMethod> {
+        let mut allowed: Vec<HttpMethod> = Vec::new();
+        for route in &self.routes {
+            if (path == "*" || route.pattern.matches(path).is_some())
+                && !allowed.contains(&route.method)
+            {
+                allowed.push(route.method);
+            }
+        }
 
-                    let path = &req.path()[7..];
-                    if path.contains("..") || path.starts_with('/') {
-                        return Ok(response::Response::new(HttpStatus::Forbidden));
-                    }
+        if allowed.is_empty() {
+            return allowed;
+        }
 
-                    match req.body() {
-                        HttpBody::Empty => Ok(response::Response::new(HttpStatus::NoContent)),
-                        HttpBody::Content(data) => {
-                            Ok(self.file_server.save(path, data).map_or_else(
-                                |e| match e {
-                                    file::FileSaverError::InvalidPath(msg) => {
-                                        response::bad_request(&msg)
-                                    }
-                                    file::FileSaverError::Other(msg) => {
-                                        response::internal_server_error(Some(&msg))
-                                    }
-                                },
-                                |()| response::Response::new(HttpStatus::Created),
-                            ))
-                        }
-                    }
-                }
-                _ => Ok(response::Response::new(HttpStatus::MethodNotAllowed)),
-            };
+        if let Some(get) = allowed.iter().position(|m| *m == HttpMethod::Get)
+            && !allowed.contains(&HttpMethod::Head)
+        {
+            allowed.insert(get + 1, HttpMethod::Head);
+        }
+        if !allowed.contains(&HttpMethod::Options) {
+            allowed.push(HttpMethod::Options);
         }
 
-        Ok(response::not_found())
+        allowed
+    }
+
+    /// Returns the first route for the method whose pattern matches the
+    /// path, with the parameters it captures.
+    fn find(&self, method: HttpMethod, path: &str) -> Option<(&Route, Vec<(String, String)>)> {
+        self.routes
+            .iter()
+            .filter(|route| route.method == method)
+            .find_map(|route| route.pattern.matches(path).map(|params| (route, params)))
     }
 }
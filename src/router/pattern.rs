@@ -0,0 +1,96 @@
+use anyhow::{Result, anyhow};
+
+use crate::http::uri;
+
+/// A path pattern such as `/users/:id/files/*rest`.
+///
+/// Literal segments must match exactly, `:name` matches a single non-empty
+/// segment and `*name`, which may only come last, matches the rest of the
+/// path including any slashes, possibly nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+    Wildcard(String),
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let rest = pattern
+            .strip_prefix('/')
+            .ok_or_else(|| anyhow!("pattern '{pattern}' doesn't start with '/'"))?;
+
+        let mut segments = Vec::new();
+        if rest.is_empty() {
+            return Ok(Self { segments });
+        }
+
+        let parts = rest.split('/').collect::<Vec<&str>>();
+        for (i, part) in parts.iter().enumerate() {
+            let segment = if let Some(name) = part.strip_prefix(':') {
+                Segment::Param(capture_name(pattern, name)?)
+            } else if let Some(name) = part.strip_prefix('*') {
+                if i != parts.len() - 1 {
+                    return Err(anyhow!("wildcard in pattern '{pattern}' isn't the last segment"));
+                }
+                Segment::Wildcard(capture_name(pattern, name)?)
+            } else if part.is_empty() {
+                return Err(anyhow!("pattern '{pattern}' has an empty segment"));
+            } else {
+                Segment::Literal(String::from(*part))
+            };
+
+            segments.push(segment);
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Matches the pattern against a request path that is still
+    /// percent-encoded, so an encoded `/` stays part of its segment. Segments
+    /// are decoded before they are compared or captured. Returns the captured
+    /// parameters in pattern order, or None if the path doesn't match.
+    #[must_use]
+    pub fn matches(&self, path: &str) -> Option<Vec<(String, String)>> {
+        // None once every segment of the path has been consumed
+        let mut rest = Some(path.strip_prefix('/')?).filter(|r| !r.is_empty());
+
+        let mut params = Vec::new();
+        for segment in &self.segments {
+            if let Segment::Wildcard(name) = segment {
+                let rest = uri::decode_path(rest.unwrap_or_default()).ok()?;
+                params.push((name.clone(), rest));
+                return Some(params);
+            }
+
+            let current = rest?;
+            let (head, tail) = current
+                .split_once('/')
+                .map_or((current, None), |(h, t)| (h, Some(t)));
+            let head = uri::decode_path(head).ok()?;
+            match segment {
+                Segment::Literal(literal) if head == *literal => {}
+                Segment::Param(name) if !head.is_empty() => {
+                    params.push((name.clone(), head));
+                }
+                _ => return None,
+            }
+            rest = tail;
+        }
+
+        rest.is_none().then_some(params)
+    }
+}
+
+fn capture_name(pattern: &str, name: &str) -> Result<String> {
+    if name.is_empty() {
+        return Err(anyhow!("pattern '{pattern}' has a capture without a name"));
+    }
+
+    Ok(String::from(name))
+}
@@ -0,0 +1,915 @@
+use std::fmt::Write;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use super::{FileOptions, FileRoutes, Pattern, Router, State};
+use crate::compression::CompressionPolicy;
+use crate::file;
+use crate::http::method::HttpMethod;
+use crate::http::status::HttpStatus;
+use crate::request::{Request, from_reader};
+use crate::response::{self, Response};
+
+// Pattern tests
+
+fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(n, v)| (String::from(*n), String::from(*v)))
+        .collect()
+}
+
+#[test]
+fn test_pattern_literal() {
+    let pattern = Pattern::parse("/user-agent").unwrap();
+
+    assert_eq!(pattern.matches("/user-agent"), Some(params(&[])));
+    assert_eq!(pattern.matches("/user-agent/"), None);
+    assert_eq!(pattern.matches("/user-agents"), None);
+    assert_eq!(pattern.matches("/"), None);
+}
+
+#[test]
+fn test_pattern_root() {
+    let pattern = Pattern::parse("/").unwrap();
+
+    assert_eq!(pattern.matches("/"), Some(params(&[])));
+    assert_eq!(pattern.matches("/a"), None);
+    assert_eq!(pattern.matches("*"), None);
+}
+
+#[test]
+fn test_pattern_params_and_wildcard() {
+    let pattern = Pattern::parse("/users/:id/files/*rest").unwrap();
+
+    assert_eq!(
+        pattern.matches("/users/42/files/a/b.txt"),
+        Some(params(&[("id", "42"), ("rest", "a/b.txt")]))
+    );
+    assert_eq!(
+        pattern.matches("/users/42/files/dir/"),
+        Some(params(&[("id", "42"), ("rest", "dir/")]))
+    );
+    assert_eq!(
+        pattern.matches("/users/42/files"),
+        Some(params(&[("id", "42"), ("rest", "")]))
+    );
+    assert_eq!(pattern.matches("/users//files/a"), None);
+    assert_eq!(pattern.matches("/users/42/other/a"), None);
+    assert_eq!(pattern.matches("/users/42"), None);
+}
+
+#[test]
+fn test_pattern_param_matches_single_segment() {
+    let pattern = Pattern::parse("/users/:id").unwrap();
+
+    assert_eq!(pattern.matches("/users/7"), Some(params(&[("id", "7")])));
+    assert_eq!(pattern.matches("/users/7/x"), None);
+    assert_eq!(pattern.matches("/users/"), None);
+}
+
+#[test]
+fn test_pattern_decodes_segments() {
+    let pattern = Pattern::parse("/users/:id/my files/*rest").unwrap();
+
+    assert_eq!(
+        pattern.matches("/users/a%2Fb/my%20files/c%2Fd/e"),
+        Some(params(&[("id", "a/b"), ("rest", "c/d/e")]))
+    );
+    assert_eq!(pattern.matches("/users/a%zz/my%20files/c"), None);
+}
+
+#[test]
+fn test_pattern_parse_errors() {
+    assert!(Pattern::parse("users").is_err());
+    assert!(Pattern::parse("/files/*rest/more").is_err());
+    assert!(Pattern::parse("/users/:").is_err());
+    assert!(Pattern::parse("/a//b").is_err());
+}
+
+// Router tests
+
+fn request(method: &str, path: &str) -> Request {
+    let raw = format!("{method} {path} HTTP/1.1\r\nUser-Agent: test\r\n\r\n");
+    from_reader(&mut Cursor::new(raw.into_bytes())).unwrap()
+}
+
+fn output(resp: Response) -> String {
+    let mut buffer = Vec::new();
+    resp.write(&mut buffer).unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+fn echo_param(name: &'static str) -> impl Fn(&Request, &State) -> anyhow::Result<Response> {
+    move |req, _state| {
+        let mut resp = response::ok();
+        resp.set_str_body(req.param(name).unwrap_or("<none>"));
+        Ok(resp)
+    }
+}
+
+fn test_router() -> Router {
+    Router::new()
+        .route(HttpMethod::Get, "/users/:id", echo_param("id"))
+        .unwrap()
+        .route(HttpMethod::Delete, "/users/:id", echo_param("id"))
+        .unwrap()
+        .route(
+            HttpMethod::Get,
+            "/users/:id/files/*rest",
+            echo_param("rest"),
+        )
+        .unwrap()
+}
+
+#[test]
+fn test_router_dispatches_with_params() {
+    let router = test_router();
+
+    let mut req = request("GET", "/users/42");
+    let out = output(router.handle(&mut req, &State::new()).unwrap());
+    assert!(out.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(out.ends_with("\r\n\r\n42"));
+    assert_eq!(req.param("id"), Some("42"));
+
+    let mut req = request("GET", "/users/42/files/a/b");
+    let out = output(router.handle(&mut req, &State::new()).unwrap());
+    assert!(out.ends_with("\r\n\r\na/b"));
+}
+
+#[test]
+fn test_router_param_with_encoded_slash() {
+    let router = test_router();
+
+    let mut req = request("GET", "/users/a%2Fb");
+    let out = output(router.handle(&mut req, &State::new()).unwrap());
+    assert!(out.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert_eq!(req.param("id"), Some("a/b"));
+}
+
+#[test]
+fn test_router_method_not_allowed_lists_allowed_methods() {
+    let router = test_router();
+
+    let out = output(
+        router
+            .handle(&mut request("POST", "/users/42"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+    assert!(out.contains("allow: GET, HEAD, DELETE, OPTIONS\r\n"));
+}
+
+#[test]
+fn test_router_not_found() {
+    let router = test_router();
+
+    let out = output(
+        router
+            .handle(&mut request("GET", "/groups/1"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    assert!(!out.contains("allow"));
+}
+
+#[test]
+fn test_router_first_matching_route_wins() {
+    let router = Router::new()
+        .route(
+            HttpMethod::Get,
+            "/users/me",
+            |_req: &Request, _state: &State| {
+                let mut resp = response::ok();
+                resp.set_str_body("me");
+                Ok(resp)
+            },
+        )
+        .unwrap()
+        .route(HttpMethod::Get, "/users/:id", echo_param("id"))
+        .unwrap();
+
+    let out = output(
+        router
+            .handle(&mut request("GET", "/users/me"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.ends_with("\r\n\r\nme"));
+    let out = output(
+        router
+            .handle(&mut request("GET", "/users/you"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.ends_with("\r\n\r\nyou"));
+}
+
+#[test]
+fn test_router_passes_state_to_handlers() {
+    struct Greeting(&'static str);
+
+    let router = Router::new()
+        .route(
+            HttpMethod::Get,
+            "/greet",
+            |_req: &Request, state: &State| {
+                let mut resp = response::ok();
+                resp.set_str_body(state.get::<Greeting>().map_or("none", |g| g.0));
+                Ok(resp)
+            },
+        )
+        .unwrap();
+
+    let mut state = State::new();
+    let out = output(
+        router
+            .handle(&mut request("GET", "/greet"), &state)
+            .unwrap(),
+    );
+    assert!(out.ends_with("\r\n\r\nnone"));
+
+    state.insert(Greeting("hello"));
+    let out = output(
+        router
+            .handle(&mut request("GET", "/greet"), &state)
+            .unwrap(),
+    );
+    assert!(out.ends_with("\r\n\r\nhello"));
+}
+
+#[test]
+fn test_state_replaces_values_of_the_same_type() {
+    let mut state = State::new();
+    assert_eq!(state.get::<u32>(), None);
+
+    state.insert(1_u32);
+    state.insert(String::from("a"));
+    state.insert(2_u32);
+    assert_eq!(state.get::<u32>(), Some(&2));
+    assert_eq!(state.get::<String>().map(String::as_str), Some("a"));
+}
+
+#[test]
+fn test_builtin_routes_are_optional() {
+    let router = Router::new().echo().unwrap();
+
+    let out = output(
+        router
+            .handle(&mut request("GET", "/echo/abc"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.ends_with("\r\n\r\nabc"));
+    let out = output(
+        router
+            .handle(&mut request("GET", "/user-agent"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    let out = output(
+        router
+            .handle(&mut request("GET", "/"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+    let router = router.user_agent().unwrap();
+    let out = output(
+        router
+            .handle(&mut request("GET", "/user-agent"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.ends_with("\r\n\r\ntest"));
+}
+
+#[test]
+fn test_router_head_falls_back_to_get() {
+    let router = test_router();
+
+    let mut req = request("HEAD", "/users/42");
+    let out = output(router.handle(&mut req, &State::new()).unwrap());
+    assert!(out.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(out.contains("content-length: 2\r\n"));
+    assert_eq!(req.param("id"), Some("42"));
+}
+
+#[test]
+fn test_router_prefers_head_route() {
+    let router = test_router()
+        .route(
+            HttpMethod::Head,
+            "/users/:id",
+            |_req: &Request, _state: &State| Ok(Response::new(HttpStatus::NoContent)),
+        )
+        .unwrap();
+
+    let out = output(
+        router
+            .handle(&mut request("HEAD", "/users/42"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.starts_with("HTTP/1.1 204 No Content\r\n"));
+}
+
+#[test]
+fn test_router_allow_lists_head_with_get() {
+    let router = test_router();
+
+    let out = output(
+        router
+            .handle(&mut request("PUT", "/users/42"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.contains("allow: GET, HEAD, DELETE, OPTIONS\r\n"));
+
+    let router = Router::new()
+        .route(HttpMethod::Delete, "/x", echo_param("x"))
+        .unwrap();
+    let out = output(
+        router
+            .handle(&mut request("HEAD", "/x"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+    assert!(out.contains("allow: DELETE, OPTIONS\r\n"));
+}
+
+#[test]
+fn test_router_answers_options_with_allowed_methods() {
+    let router = test_router();
+
+    let out = output(
+        router
+            .handle(&mut request("OPTIONS", "/users/42"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.starts_with("HTTP/1.1 204 No Content\r\n"));
+    assert!(out.contains("allow: GET, HEAD, DELETE, OPTIONS\r\n"));
+
+    let out = output(
+        router
+            .handle(&mut request("OPTIONS", "/groups"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
+
+#[test]
+fn test_router_answers_options_for_the_server() {
+    let router = test_router()
+        .route(HttpMethod::Post, "/groups", echo_param("x"))
+        .unwrap();
+
+    let out = output(
+        router
+            .handle(&mut request("OPTIONS", "*"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.starts_with("HTTP/1.1 204 No Content\r\n"));
+    assert!(out.contains("allow: GET, HEAD, DELETE, POST, OPTIONS\r\n"));
+
+    let out = output(
+        Router::new()
+            .handle(&mut request("OPTIONS", "*"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
+
+#[test]
+fn test_router_prefers_options_route() {
+    let router = test_router()
+        .route(HttpMethod::Options, "/users/:id", echo_param("id"))
+        .unwrap();
+
+    let out = output(
+        router
+            .handle(&mut request("OPTIONS", "/users/7"), &State::new())
+            .unwrap(),
+    );
+    assert!(out.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(out.ends_with("\r\n\r\n7"));
+}
+
+// File route tests
+
+/// Returns a router serving a fresh temp directory under /files.
+fn file_router(name: &str) -> (Router, PathBuf) {
+    file_router_with(name, FileOptions::default())
+}
+
+fn file_router_with(name: &str, options: FileOptions) -> (Router, PathBuf) {
+    let root = std::env::temp_dir().join(format!("router-test-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+
+    let file_server = file::create(Some(root.display().to_string()), true, false).unwrap();
+    let router = Router::new()
+        .files(FileRoutes::new(file_server, options))
+        .unwrap();
+    (router, root)
+}
+
+fn file_request(method: &str, path: &str, headers: &[(&str, &str)], body: &[u8]) -> Request {
+    let mut raw = format!("{method} {path} HTTP/1.1\r\n");
+    for (name, value) in headers {
+        let _ = write!(raw, "{name}: {value}\r\n");
+    }
+    if !body.is_empty() {
+        let _ = write!(raw, "Content-Length: {}\r\n", body.len());
+    }
+    raw.push_str("\r\n");
+
+    let mut raw = raw.into_bytes();
+    raw.extend_from_slice(body);
+    from_reader(&mut Cursor::new(raw)).unwrap()
+}
+
+/// Routes a request and returns the head of the response and its body,
+/// with any chunked framing removed.
+fn send(router: &Router, mut req: Request) -> (String, Vec<u8>) {
+    let mut buffer = Vec::new();
+    router
+        .handle(&mut req, &State::new())
+        .unwrap()
+        .write(&mut buffer)
+        .unwrap();
+
+    let split = buffer.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    let head = String::from_utf8(buffer[..split].to_vec()).unwrap();
+    let body = if head.contains("transfer-encoding: chunked\r\n") {
+        dechunk(&buffer[split..])
+    } else {
+        buffer[split..].to_vec()
+    };
+    (head, body)
+}
+
+fn dechunk(mut remaining: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    loop {
+        let line_end = remaining.windows(2).position(|w| w == b"\r\n").unwrap();
+        let size = std::str::from_utf8(&remaining[..line_end]).unwrap();
+        let size = usize::from_str_radix(size, 16).unwrap();
+        if size == 0 {
+            return data;
+        }
+        data.extend_from_slice(&remaining[line_end + 2..line_end + 2 + size]);
+        remaining = &remaining[line_end + 2 + size + 2..];
+    }
+}
+
+/// Writes a file modified an hour ago, so it has a strong entity tag.
+fn write_settled(path: &std::path::Path, content: &str) {
+    std::fs::write(path, content).unwrap();
+    let modified = std::time::SystemTime::now() - std::time::Duration::from_hours(1);
+    std::fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+}
+
+/// Returns the value of a header in the head of a response.
+fn header_value<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines().find_map(|line| {
+        let (n, v) = line.split_once(": ")?;
+        n.eq_ignore_ascii_case(name).then_some(v)
+    })
+}
+
+#[test]
+fn test_files_single_range() {
+    let (router, root) = file_router("single-range");
+    std::fs::write(root.join("data.txt"), "0123456789").unwrap();
+
+    let req = file_request("GET", "/files/data.txt", &[("Range", "bytes=2-4")], b"");
+    let (head, body) = send(&router, req);
+
+    assert!(head.starts_with("HTTP/1.1 206 Partial Content\r\n"));
+    assert_eq!(header_value(&head, "content-range"), Some("bytes 2-4/10"));
+    assert_eq!(header_value(&head, "accept-ranges"), Some("bytes"));
+    assert_eq!(body, b"234");
+}
+
+#[test]
+fn test_files_reject_encoded_slash() {
+    let (router, root) = file_router("encoded-slash");
+    std::fs::create_dir(root.join("dir")).unwrap();
+    std::fs::write(root.join("dir").join("data.txt"), "0123456789").unwrap();
+
+    let req = file_request("GET", "/files/dir%2Fdata.txt", &[], b"");
+    let (head, _) = send(&router, req);
+    assert!(head.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+    let req = file_request("GET", "/files/dir/data.txt", &[], b"");
+    let (head, body) = send(&router, req);
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert_eq!(body, b"0123456789");
+}
+
+#[test]
+fn test_files_multiple_ranges() {
+    let (router, root) = file_router("multi-range");
+    std::fs::write(root.join("data.txt"), "0123456789").unwrap();
+
+    let req = file_request("GET", "/files/data.txt", &[("Range", "bytes=0-1,5-6")], b"");
+    let (head, body) = send(&router, req);
+    let body = String::from_utf8(body).unwrap();
+
+    assert!(head.starts_with("HTTP/1.1 206 Partial Content\r\n"));
+    assert!(header_value(&head, "content-range").is_none());
+    let boundary = header_value(&head, "content-type")
+        .and_then(|v| v.strip_prefix("multipart/byteranges; boundary="))
+        .unwrap();
+    assert!(body.contains(&format!("--{boundary}\r\n")));
+    assert!(body.contains("Content-Range: bytes 0-1/10\r\n\r\n01\r\n"));
+    assert!(body.contains("Content-Range: bytes 5-6/10\r\n\r\n56\r\n"));
+    assert!(body.ends_with(&format!("--{boundary}--\r\n")));
+}
+
+#[test]
+fn test_files_unsatisfiable_range() {
+    let (router, root) = file_router("unsatisfiable-range");
+    std::fs::write(root.join("data.txt"), "0123456789").unwrap();
+
+    let req = file_request("GET", "/files/data.txt", &[("Range", "bytes=20-30")], b"");
+    let (head, body) = send(&router, req);
+
+    assert!(head.starts_with("HTTP/1.1 416 Range Not Satisfiable\r\n"));
+    assert_eq!(header_value(&head, "content-range"), Some("bytes */10"));
+    assert!(body.is_empty());
+}
+
+#[test]
+fn test_files_stale_if_range_sends_full_file() {
+    let (router, root) = file_router("stale-if-range");
+    std::fs::write(root.join("data.txt"), "0123456789").unwrap();
+
+    let headers = [("Range", "bytes=2-4"), ("If-Range", "\"outdated\"")];
+    let req = file_request("GET", "/files/data.txt", &headers, b"");
+    let (head, body) = send(&router, req);
+
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(header_value(&head, "content-range").is_none());
+    assert_eq!(body, b"0123456789");
+}
+
+#[test]
+fn test_files_send_validators() {
+    let (router, root) = file_router("validators");
+    write_settled(&root.join("data.txt"), "0123456789");
+    std::fs::write(root.join("fresh.txt"), "0123456789").unwrap();
+
+    let (head, _) = send(&router, file_request("GET", "/files/data.txt", &[], b""));
+
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(header_value(&head, "etag").is_some_and(|v| v.starts_with('"')));
+    assert!(header_value(&head, "last-modified").is_some_and(|v| v.ends_with(" GMT")));
+
+    // a file that was just written may change again within the same second
+    let (head, _) = send(&router, file_request("GET", "/files/fresh.txt", &[], b""));
+    assert!(header_value(&head, "etag").is_some_and(|v| v.starts_with("W/\"")));
+}
+
+#[test]
+fn test_files_not_modified() {
+    let (router, root) = file_router("not-modified");
+    std::fs::write(root.join("data.txt"), "0123456789").unwrap();
+    let (head, _) = send(&router, file_request("GET", "/files/data.txt", &[], b""));
+    let etag = header_value(&head, "etag").unwrap();
+    let last_modified = header_value(&head, "last-modified").unwrap();
+
+    for headers in [
+        [("If-None-Match", etag)],
+        [("If-Modified-Since", last_modified)],
+    ] {
+        let req = file_request("GET", "/files/data.txt", &headers, b"");
+        let (not_modified, body) = send(&router, req);
+
+        assert!(not_modified.starts_with("HTTP/1.1 304 Not Modified\r\n"));
+        assert_eq!(header_value(&not_modified, "etag"), Some(etag));
+        assert!(body.is_empty());
+    }
+}
+
+#[test]
+fn test_files_precondition_failed() {
+    let (router, root) = file_router("precondition-failed");
+    std::fs::write(root.join("data.txt"), "0123456789").unwrap();
+
+    let cases = [
+        ("If-Match", "\"outdated\""),
+        ("If-Unmodified-Since", "Thu, 01 Jan 1970 00:00:00 GMT"),
+    ];
+    for header in cases {
+        let req = file_request("GET", "/files/data.txt", &[header], b"");
+        let (head, body) = send(&router, req);
+
+        assert!(head.starts_with("HTTP/1.1 412 Precondition Failed\r\n"));
+        assert!(body.is_empty());
+    }
+}
+
+#[test]
+fn test_files_put_creates_then_replaces() {
+    let (router, root) = file_router("put");
+
+    let (head, _) = send(
+        &router,
+        file_request("PUT", "/files/new.txt", &[], b"first"),
+    );
+    assert!(head.starts_with("HTTP/1.1 201 Created\r\n"));
+
+    let (head, _) = send(
+        &router,
+        file_request("PUT", "/files/new.txt", &[], b"second"),
+    );
+    assert!(head.starts_with("HTTP/1.1 204 No Content\r\n"));
+    assert_eq!(std::fs::read(root.join("new.txt")).unwrap(), b"second");
+}
+
+#[test]
+fn test_files_put_preconditions() {
+    let (router, root) = file_router("put-preconditions");
+    write_settled(&root.join("data.txt"), "original");
+    let (head, _) = send(&router, file_request("GET", "/files/data.txt", &[], b""));
+    let etag = header_value(&head, "etag").unwrap();
+
+    // creating a file that must not exist, or replacing an outdated one
+    let failed = [
+        ("/files/data.txt", ("If-None-Match", "*")),
+        ("/files/data.txt", ("If-Match", "\"outdated\"")),
+        ("/files/missing.txt", ("If-Match", "*")),
+    ];
+    for (path, header) in failed {
+        let (head, _) = send(&router, file_request("PUT", path, &[header], b"new"));
+        assert!(head.starts_with("HTTP/1.1 412 Precondition Failed\r\n"));
+    }
+    assert_eq!(std::fs::read(root.join("data.txt")).unwrap(), b"original");
+    assert!(!root.join("missing.txt").exists());
+
+    let req = file_request("PUT", "/files/data.txt", &[("If-Match", etag)], b"updated");
+    let (head, _) = send(&router, req);
+    assert!(head.starts_with("HTTP/1.1 204 No Content\r\n"));
+
+    let req = file_request("PUT", "/files/other.txt", &[("If-None-Match", "*")], b"new");
+    let (head, _) = send(&router, req);
+    assert!(head.starts_with("HTTP/1.1 201 Created\r\n"));
+}
+
+#[test]
+fn test_files_patch_and_delete_preconditions() {
+    let (router, root) = file_router("modify-preconditions");
+    write_settled(&root.join("data.txt"), "original");
+    let (head, _) = send(&router, file_request("GET", "/files/data.txt", &[], b""));
+    let etag = header_value(&head, "etag").unwrap();
+
+    // changing an outdated file, or one that must exist but doesn't
+    let failed = [
+        ("/files/data.txt", ("If-Match", "\"outdated\"")),
+        ("/files/missing.txt", ("If-Match", "*")),
+    ];
+    for method in ["PATCH", "DELETE"] {
+        for (path, header) in failed {
+            let (head, _) = send(&router, file_request(method, path, &[header], b"!"));
+            assert!(head.starts_with("HTTP/1.1 412 Precondition Failed\r\n"));
+        }
+    }
+    assert_eq!(std::fs::read(root.join("data.txt")).unwrap(), b"original");
+
+    let req = file_request("PATCH", "/files/data.txt", &[("If-Match", etag)], b"!");
+    let (head, _) = send(&router, req);
+    assert!(head.starts_with("HTTP/1.1 204 No Content\r\n"));
+    assert_eq!(std::fs::read(root.join("data.txt")).unwrap(), b"original!");
+
+    // the file changed, so the tag from before the PATCH is outdated
+    let req = file_request("DELETE", "/files/data.txt", &[("If-Match", etag)], b"");
+    let (head, _) = send(&router, req);
+    assert!(head.starts_with("HTTP/1.1 412 Precondition Failed\r\n"));
+    assert!(root.join("data.txt").exists());
+
+    let req = file_request("DELETE", "/files/data.txt", &[("If-Match", "*")], b"");
+    let (head, _) = send(&router, req);
+    assert!(head.starts_with("HTTP/1.1 204 No Content\r\n"));
+    assert!(!root.join("data.txt").exists());
+}
+
+#[test]
+fn test_files_patch() {
+    let (router, root) = file_router("patch");
+    std::fs::write(root.join("data.txt"), "0123456789").unwrap();
+
+    let req = file_request(
+        "PATCH",
+        "/files/data.txt",
+        &[("Content-Range", "bytes 2-3/*")],
+        b"ab",
+    );
+    let (head, _) = send(&router, req);
+    assert!(head.starts_with("HTTP/1.1 204 No Content\r\n"));
+
+    // without a Content-Range the body is appended
+    let (head, _) = send(
+        &router,
+        file_request("PATCH", "/files/data.txt", &[], b"XY"),
+    );
+    assert!(head.starts_with("HTTP/1.1 204 No Content\r\n"));
+    assert_eq!(
+        std::fs::read(root.join("data.txt")).unwrap(),
+        b"01ab456789XY"
+    );
+}
+
+#[test]
+fn test_files_patch_rejects_mismatched_content_range() {
+    let (router, root) = file_router("patch-mismatch");
+    std::fs::write(root.join("data.txt"), "0123456789").unwrap();
+
+    let req = file_request(
+        "PATCH",
+        "/files/data.txt",
+        &[("Content-Range", "bytes 2-5/*")],
+        b"ab",
+    );
+    let (head, _) = send(&router, req);
+
+    assert!(head.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    assert_eq!(std::fs::read(root.join("data.txt")).unwrap(), b"0123456789");
+}
+
+#[test]
+fn test_files_delete() {
+    let (router, root) = file_router("delete");
+    std::fs::write(root.join("data.txt"), "0123456789").unwrap();
+
+    let (head, _) = send(&router, file_request("DELETE", "/files/data.txt", &[], b""));
+    assert!(head.starts_with("HTTP/1.1 204 No Content\r\n"));
+    assert!(!root.join("data.txt").exists());
+
+    let (head, _) = send(&router, file_request("DELETE", "/files/data.txt", &[], b""));
+    assert!(head.starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
+
+#[test]
+fn test_files_serve_fresh_precompressed_sibling() {
+    let (router, root) = file_router("precompressed");
+    write_settled(&root.join("app.js"), "original");
+    std::fs::write(root.join("app.js.gz"), "gzipped").unwrap();
+
+    let req = file_request("GET", "/files/app.js", &[("Accept-Encoding", "gzip")], b"");
+    let (head, body) = send(&router, req);
+
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert_eq!(header_value(&head, "content-encoding"), Some("gzip"));
+    assert_eq!(header_value(&head, "vary"), Some("Accept-Encoding"));
+    assert_eq!(body, b"gzipped");
+
+    // a client that doesn't accept the coding still learns the response varies
+    let (head, body) = send(&router, file_request("GET", "/files/app.js", &[], b""));
+    assert!(header_value(&head, "content-encoding").is_none());
+    assert_eq!(header_value(&head, "vary"), Some("Accept-Encoding"));
+    assert_eq!(body, b"original");
+}
+
+#[test]
+fn test_files_skip_stale_precompressed_sibling() {
+    let (router, root) = file_router("stale-precompressed");
+    write_settled(&root.join("app.js.gz"), "outdated");
+    std::fs::write(root.join("app.js"), "original").unwrap();
+
+    let req = file_request("GET", "/files/app.js", &[("Accept-Encoding", "gzip")], b"");
+    let (head, body) = send(&router, req);
+
+    assert!(header_value(&head, "content-encoding").is_none());
+    assert_eq!(header_value(&head, "vary"), Some("Accept-Encoding"));
+    assert_eq!(body, b"original");
+}
+
+#[test]
+fn test_files_precompressed_sibling_follows_compression_policy() {
+    for (name, compression) in [
+        (
+            "precompressed-disabled",
+            CompressionPolicy {
+                enabled: false,
+                ..CompressionPolicy::default()
+            },
+        ),
+        (
+            "precompressed-excluded",
+            CompressionPolicy {
+                excluded_routes: vec![String::from("/files")],
+                ..CompressionPolicy::default()
+            },
+        ),
+    ] {
+        let options = FileOptions {
+            compression,
+            ..FileOptions::default()
+        };
+        let (router, root) = file_router_with(name, options);
+        write_settled(&root.join("app.js"), "original");
+        std::fs::write(root.join("app.js.gz"), "gzipped").unwrap();
+
+        let req = file_request("GET", "/files/app.js", &[("Accept-Encoding", "gzip")], b"");
+        let (head, body) = send(&router, req);
+
+        assert!(header_value(&head, "content-encoding").is_none(), "{name}");
+        assert!(header_value(&head, "vary").is_none(), "{name}");
+        assert_eq!(body, b"original");
+    }
+}
+
+#[test]
+fn test_files_unsatisfiable_range_of_sibling_has_no_encoding() {
+    let (router, root) = file_router("precompressed-416");
+    write_settled(&root.join("app.js"), "original");
+    std::fs::write(root.join("app.js.gz"), "gzipped").unwrap();
+
+    let headers = [("Accept-Encoding", "gzip"), ("Range", "bytes=100-")];
+    let (head, _) = send(&router, file_request("GET", "/files/app.js", &headers, b""));
+
+    assert!(head.starts_with("HTTP/1.1 416 Range Not Satisfiable\r\n"));
+    assert!(header_value(&head, "content-encoding").is_none());
+}
+
+#[test]
+fn test_files_listing_varies_by_accept() {
+    let options = FileOptions {
+        autoindex: true,
+        ..FileOptions::default()
+    };
+    let (router, root) = file_router_with("listing", options);
+    std::fs::write(root.join("data.txt"), "0123456789").unwrap();
+    std::fs::write(root.join(".data.txt.123-0.part"), "01234").unwrap();
+
+    let (head, body) = send(&router, file_request("GET", "/files/", &[], b""));
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert_eq!(header_value(&head, "content-type"), Some("text/html"));
+    assert_eq!(header_value(&head, "vary"), Some("Accept"));
+    assert!(!String::from_utf8(body).unwrap().contains(".part"));
+
+    let req = file_request("GET", "/files/", &[("Accept", "application/json")], b"");
+    let (head, body) = send(&router, req);
+    assert_eq!(
+        header_value(&head, "content-type"),
+        Some("application/json")
+    );
+    assert_eq!(header_value(&head, "vary"), Some("Accept"));
+    assert!(
+        String::from_utf8(body)
+            .unwrap()
+            .contains("\"name\":\"data.txt\"")
+    );
+}
+
+#[test]
+fn test_files_listing_negotiates_quality_values() {
+    let options = FileOptions {
+        autoindex: true,
+        ..FileOptions::default()
+    };
+    let (router, root) = file_router_with("listing-quality", options);
+    std::fs::write(root.join("data.txt"), "0123456789").unwrap();
+
+    let cases = [
+        ("application/json;q=0", "text/html"),
+        ("text/html;q=0.5, application/json", "application/json"),
+        ("text/html, application/json", "text/html"),
+        ("application/*, text/html;q=0.1", "application/json"),
+        ("text/html,application/xhtml+xml,*/*;q=0.8", "text/html"),
+        ("*/*", "text/html"),
+    ];
+    for (accept, content_type) in cases {
+        let req = file_request("GET", "/files/", &[("Accept", accept)], b"");
+        let (head, _) = send(&router, req);
+        assert_eq!(
+            header_value(&head, "content-type"),
+            Some(content_type),
+            "{accept}"
+        );
+    }
+}
+
+#[test]
+fn test_files_index_file_redirects_to_trailing_slash() {
+    let options = FileOptions {
+        index_file: Some(String::from("index.html")),
+        ..FileOptions::default()
+    };
+    let (router, root) = file_router_with("index-redirect", options);
+    std::fs::create_dir(root.join("my dir")).unwrap();
+    std::fs::write(root.join("my dir").join("index.html"), "<p>hi</p>").unwrap();
+
+    let req = file_request("GET", "/files/my%20dir?x=1", &[], b"");
+    let (head, _) = send(&router, req);
+    assert!(head.starts_with("HTTP/1.1 301 Moved Permanently\r\n"));
+    assert_eq!(
+        header_value(&head, "location"),
+        Some("/files/my%20dir/?x=1")
+    );
+
+    let (head, body) = send(&router, file_request("GET", "/files/my%20dir/", &[], b""));
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert_eq!(body, b"<p>hi</p>");
+}
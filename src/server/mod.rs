@@ -2,74 +2,328 @@
  * This module defines a HttpServer that handles connection.
  */
 
+mod shutdown;
+mod timeout;
+
+#[cfg(test)]
+mod tests;
+
 use crate::connection::LineStream;
-use crate::request;
-use crate::router::Router;
-use anyhow::Result;
-use std::net::TcpStream;
+use crate::consts;
+use crate::file::FileSystem;
+use crate::http::method::HttpMethod;
+use crate::http::status::HttpStatus;
+use crate::middleware::{Chain, Middleware};
+use crate::request::{self, Request, RequestError, RequestLimits};
+use crate::response::{self, Response};
+use crate::router::{FileOptions, FileRoutes, Handler, Router, State};
+use anyhow::{Result, anyhow};
+use std::any::Any;
+use std::net::{TcpListener, TcpStream};
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 use threadpool::ThreadPool;
 
+use shutdown::IdleConnections;
+pub use shutdown::ShutdownHandle;
+use timeout::{Phase, TimedStream, Timeouts};
+
+/// Settings that control where the server listens, how many
+/// connections it serves concurrently, how large and slow a request may be
+/// and how it shuts down.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub bind: String,
+    pub port: u16,
+    pub workers: u16,
+    /// How long in-flight connections may keep running after shutdown is requested.
+    pub drain_timeout: Duration,
+    /// Size limits enforced while a request is parsed.
+    pub limits: RequestLimits,
+    /// How long a keep-alive connection may wait for its next request before
+    /// it is closed. Advertised to clients in the `Keep-Alive` header.
+    pub keep_alive_timeout: Duration,
+    /// How long a client has to send the request line and headers once the
+    /// request has started, answered with 408 when exceeded.
+    pub header_timeout: Duration,
+    /// How long a client has to send the body once the headers are in,
+    /// answered with 408 when exceeded.
+    pub body_timeout: Duration,
+    /// Each this many bytes of body received extend `body_timeout` by a
+    /// second. Zero means the body has to arrive within `body_timeout`.
+    pub min_body_rate: u64,
+}
+
+impl ServerConfig {
+    /// Checks that the settings can be served with. A zero timeout would fail
+    /// every connection or time out every request right away.
+    pub fn validate(&self) -> Result<()> {
+        let timeouts = [
+            ("keep_alive_timeout", self.keep_alive_timeout),
+            ("header_timeout", self.header_timeout),
+            ("body_timeout", self.body_timeout),
+        ];
+        for (name, timeout) in timeouts {
+            if timeout.is_zero() {
+                return Err(anyhow!("{name} must not be zero"));
+            }
+        }
+
+        Ok(())
+    }
+
+    const fn timeouts(&self) -> Timeouts {
+        Timeouts {
+            idle: self.keep_alive_timeout,
+            headers: self.header_timeout,
+            body: self.body_timeout,
+            min_body_rate: self.min_body_rate,
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: String::from("127.0.0.1"),
+            port: 4221,
+            workers: 16,
+            drain_timeout: Duration::from_secs(30),
+            limits: RequestLimits::default(),
+            keep_alive_timeout: Duration::from_secs(5),
+            header_timeout: Duration::from_secs(10),
+            body_timeout: Duration::from_secs(30),
+            min_body_rate: 500,
+        }
+    }
+}
+
+/// How often the drain checks whether the workers have finished.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 pub struct HttpServer {
     router: Arc<Router>,
+    state: Arc<State>,
+    middlewares: Arc<Chain>,
     pool: ThreadPool,
+    config: Arc<ServerConfig>,
+    shutdown: ShutdownHandle,
+    idle: Arc<IdleConnections>,
 }
 
 impl HttpServer {
-    pub fn new(router: Router) -> Self {
+    /// Creates a server for `router`, failing if the config doesn't pass
+    /// `ServerConfig::validate`.
+    pub fn new(router: Router, config: ServerConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self::with_state(router, Chain::new(), State::new(), config))
+    }
+
+    /// Returns a builder to configure the server and mount handlers and
+    /// middlewares. Nothing is mounted by default, not even the built-ins.
+    ///
+    /// The server closes a connection after a response when the client sent
+    /// `Connection: close` or the response carries it. Closing for any other
+    /// reason, e.g. `ConnectionManager` on shutdown, is left to middlewares.
+    #[must_use]
+    pub fn builder() -> HttpServerBuilder {
+        HttpServerBuilder::default()
+    }
+
+    fn with_state(
+        router: Router,
+        middlewares: Chain,
+        mut state: State,
+        config: ServerConfig,
+    ) -> Self {
+        // middlewares find out about a shutdown through the state
+        let shutdown = ShutdownHandle::default();
+        state.insert(shutdown.clone());
+
         Self {
             router: Arc::new(router),
-            pool: ThreadPool::new(16),
+            state: Arc::new(state),
+            middlewares: Arc::new(middlewares),
+            pool: ThreadPool::new(usize::from(config.workers.max(1))),
+            config: Arc::new(config),
+            shutdown,
+            idle: Arc::new(IdleConnections::default()),
+        }
+    }
+
+    /// Returns a handle that can be used to stop the server from another thread,
+    /// e.g. a signal handler.
+    #[must_use]
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Binds a listener to the configured address and serves connections
+    /// until shutdown is requested, then drains in-flight connections.
+    pub fn run(&self) -> Result<()> {
+        self.serve(self.bind()?)
+    }
+
+    /// Binds a listener to the configured address. With port 0 the OS picks
+    /// a free port, which `TcpListener::local_addr` tells.
+    pub fn bind(&self) -> Result<TcpListener> {
+        Ok(TcpListener::bind((
+            self.config.bind.as_str(),
+            self.config.port,
+        ))?)
+    }
+
+    /// Serves connections from `listener` until shutdown is requested, then
+    /// drains in-flight connections.
+    pub fn serve(&self, listener: TcpListener) -> Result<()> {
+        println!("Server started at {}", listener.local_addr()?);
+
+        // shutting down connects to the listener, which wakes `accept`
+        self.shutdown.wake_on_shutdown(listener.local_addr()?);
+
+        while !self.shutdown.is_shutdown() {
+            match listener.accept() {
+                // the connection that woke the loop isn't served
+                Ok(_) if self.shutdown.is_shutdown() => break,
+                Ok((stream, _)) => self.handle(stream),
+                Err(e) => {
+                    println!("error: {e}");
+                }
+            }
         }
+
+        drop(listener);
+        println!("Shutting down, no longer accepting connections");
+        self.drain();
+
+        Ok(())
+    }
+
+    /// Waits for the workers to finish their connections, up to the configured
+    /// drain timeout.
+    fn drain(&self) {
+        self.idle.close_all();
+
+        let deadline = Instant::now() + self.config.drain_timeout;
+        while self.pool.active_count() + self.pool.queued_count() > 0 {
+            if Instant::now() >= deadline {
+                println!(
+                    "Drain timeout reached with {} connection(s) still active",
+                    self.pool.active_count()
+                );
+                return;
+            }
+
+            // connections that went idle after the first sweep are closed here
+            self.idle.close_all();
+            thread::sleep(DRAIN_POLL_INTERVAL);
+        }
+
+        println!("All connections drained");
     }
 
     /// Handle a TCP connection by dispatching it to the thread pool.
     pub fn handle(&self, stream: TcpStream) {
         let router = Arc::clone(&self.router);
+        let state = Arc::clone(&self.state);
+        let middlewares = Arc::clone(&self.middlewares);
+        let shutdown = self.shutdown.clone();
+        let idle = Arc::clone(&self.idle);
+        let config = Arc::clone(&self.config);
         self.pool.execute(move || {
-            if let Err(e) = Self::handle_connection(&router, stream) {
+            if let Err(e) = Self::handle_connection(
+                &router,
+                &middlewares,
+                &state,
+                &shutdown,
+                &idle,
+                &config,
+                stream,
+            ) {
                 eprintln!("error handling connection: {e}");
             }
         });
     }
 
-    fn handle_connection(router: &Arc<Router>, mut stream: TcpStream) -> Result<()> {
+    fn handle_connection(
+        router: &Router,
+        middlewares: &Chain,
+        state: &State,
+        shutdown: &ShutdownHandle,
+        idle: &IdleConnections,
+        config: &ServerConfig,
+        stream: TcpStream,
+    ) -> Result<()> {
         let remote_addr = &stream.peer_addr()?;
         println!("Accepted connection from {remote_addr:?}");
 
+        // a second handle on the socket lets the server close it while idle
+        let control = stream.try_clone()?;
+        let mut stream = TimedStream::new(stream, config.timeouts());
         let mut line_stream = LineStream::new(&mut stream);
 
         loop {
+            // Register as idle before checking the flag, so a shutdown that
+            // starts in between either sees this connection or is seen here.
+            let idle_id = idle.register(&control);
+            if shutdown.is_shutdown() {
+                if let Some(id) = idle_id {
+                    idle.unregister(id);
+                }
+                break;
+            }
+
+            // Only a connection that hasn't started its next request is idle;
+            // one that has must be allowed to finish it during a drain.
+            line_stream.get_mut().await_request();
+            let ready = line_stream.wait_for_data();
+            if let Some(id) = idle_id {
+                idle.unregister(id);
+            }
+            match ready {
+                Ok(true) => {}
+                // the client closed the connection or it stayed idle too long
+                Ok(false) | Err(_) => break,
+            }
+
             println!("Start handling request from {remote_addr:?}");
             // Try to read the next request; break if client closed connection or error occurred
-            let Some(req) = request::from_line_stream(&mut line_stream).ok() else {
-                break;
+            let next = request::read_head(&mut line_stream, &config.limits).and_then(|head| {
+                line_stream.get_mut().start_body();
+                head.read_body(&mut line_stream, &config.limits)
+            });
+            let mut req = match next {
+                Ok(req) => req,
+                Err(e) => {
+                    if let Some(mut resp) = read_error_response(&e, line_stream.get_mut().phase()) {
+                        resp.set_header(consts::HEADER_CONNECTION, "close");
+                        resp.write(line_stream.get_mut().get_mut())?;
+                    }
+                    break;
+                }
             };
 
-            // Check if client requested connection close
-            let should_close = req
-                .headers()
-                .connection()
-                .is_some_and(|v| v.eq_ignore_ascii_case("close"));
-
             // Handle the request and write response
-            let mut resp = router.handle(&req)?;
-
-            // set the content encoding headers
-            if req
-                .headers()
-                .accept_encodings()
-                .is_some_and(|values| values.iter().any(|v| v == "gzip"))
-            {
-                resp.compress("gzip")?;
+            let mut resp = middlewares.handle(router, &mut req, state)?;
+            if *req.method() == HttpMethod::Head {
+                resp.omit_body();
             }
 
-            // set the connection management headers
+            // the protocol requires closing when the client asks to, any
+            // other reason is up to the middleware that marks the response
+            let should_close = closes_connection(&req, &resp);
             if should_close {
-                resp.set_header("Connection", "close");
+                resp.set_header(consts::HEADER_CONNECTION, "close");
+            } else {
+                // rounded up, so a sub-second timeout isn't advertised as 0
+                let idle = config.keep_alive_timeout;
+                let timeout = idle.as_secs() + u64::from(idle.subsec_nanos() > 0);
+                resp.set_header(consts::HEADER_KEEP_ALIVE, &format!("timeout={timeout}"));
             }
 
-            resp.write(&mut line_stream)?;
+            // write to the socket itself so file bodies can be sent with sendfile
+            resp.write(line_stream.get_mut().get_mut())?;
 
             // Close connection if requested
             if should_close {
@@ -81,3 +335,118 @@ impl HttpServer {
         Ok(())
     }
 }
+
+/// Configures an `HttpServer` and the handlers it mounts.
+#[derive(Default)]
+pub struct HttpServerBuilder {
+    config: ServerConfig,
+    router: Router,
+    middlewares: Chain,
+    state: State,
+}
+
+impl HttpServerBuilder {
+    /// Sets the config, failing if it doesn't pass `ServerConfig::validate`.
+    pub fn config(mut self, config: ServerConfig) -> Result<Self> {
+        config.validate()?;
+        self.config = config;
+        Ok(self)
+    }
+
+    /// Stores a value in the state passed to every handler, replacing any
+    /// value of the same type.
+    #[must_use]
+    pub fn state<T: Any + Send + Sync>(mut self, value: T) -> Self {
+        self.state.insert(value);
+        self
+    }
+
+    /// Appends a middleware to the chain wrapped around the router. The
+    /// middleware added first sees requests first and responses last.
+    #[must_use]
+    pub fn middleware<M: Middleware + 'static>(mut self, middleware: M) -> Self {
+        self.middlewares.push(middleware);
+        self
+    }
+
+    /// Mounts a handler for requests with the method whose path matches the
+    /// pattern. Routes are tried in the order they were mounted.
+    pub fn route<H>(mut self, method: HttpMethod, pattern: &str, handler: H) -> Result<Self>
+    where
+        H: Handler + 'static,
+    {
+        self.router = self.router.route(method, pattern, handler)?;
+        Ok(self)
+    }
+
+    /// Mounts the built-in `GET /` handler.
+    pub fn index(mut self) -> Result<Self> {
+        self.router = self.router.index()?;
+        Ok(self)
+    }
+
+    /// Mounts the built-in `GET /echo/*message` handler.
+    pub fn echo(mut self) -> Result<Self> {
+        self.router = self.router.echo()?;
+        Ok(self)
+    }
+
+    /// Mounts the built-in `GET /user-agent` handler.
+    pub fn user_agent(mut self) -> Result<Self> {
+        self.router = self.router.user_agent()?;
+        Ok(self)
+    }
+
+    /// Mounts the built-in /files handlers serving `file_server`.
+    pub fn files(
+        mut self,
+        file_server: Box<dyn FileSystem + Send + Sync>,
+        options: FileOptions,
+    ) -> Result<Self> {
+        self.router = self.router.files(FileRoutes::new(file_server, options))?;
+        Ok(self)
+    }
+
+    #[must_use]
+    pub fn build(self) -> HttpServer {
+        HttpServer::with_state(self.router, self.middlewares, self.state, self.config)
+    }
+}
+
+/// Returns true if the connection has to be closed after the response.
+fn closes_connection(req: &Request, resp: &Response) -> bool {
+    let is_close = |v: &str| v.eq_ignore_ascii_case("close");
+
+    req.headers().connection().is_some_and(is_close)
+        || resp.header(consts::HEADER_CONNECTION).is_some_and(is_close)
+}
+
+/// Builds the response to a failed read of a request, if there is one.
+///
+/// Malformed requests the parser could make sense of are answered, and so are
+/// requests that stalled. On anything else, e.g. a closed socket or a
+/// keep-alive connection that stayed idle, the connection is just closed.
+fn read_error_response(e: &anyhow::Error, phase: Phase) -> Option<Response> {
+    if let Some(e) = e.downcast_ref::<RequestError>() {
+        return Some(request_error_response(e));
+    }
+
+    if timeout::is_timeout(e) && phase != Phase::Idle {
+        return Some(Response::new(HttpStatus::RequestTimeout));
+    }
+
+    None
+}
+
+/// Builds the response to a request that couldn't be parsed.
+fn request_error_response(e: &RequestError) -> Response {
+    match e {
+        RequestError::InvalidTarget(_)
+        | RequestError::InvalidChunk(_)
+        | RequestError::ConflictingFraming => response::bad_request(&e.to_string()),
+        RequestError::RequestLineTooLong => Response::new(HttpStatus::UriTooLong),
+        RequestError::HeadersTooLarge => Response::new(HttpStatus::RequestHeaderFieldsTooLarge),
+        RequestError::BodyTooLarge => Response::new(HttpStatus::ContentTooLarge),
+        RequestError::UnsupportedTransferCoding(_) => Response::new(HttpStatus::NotImplemented),
+    }
+}
@@ -0,0 +1,92 @@
+/*
+ * This module provides the pieces the server uses to stop gracefully: a flag
+ * that signals shutdown and a registry of connections idling between requests.
+ */
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A cloneable handle used to ask a running `HttpServer` to stop.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownHandle {
+    flag: Arc<AtomicBool>,
+    // the address of the listener the server blocks on in `accept`
+    listener: Arc<Mutex<Option<SocketAddr>>>,
+}
+
+impl ShutdownHandle {
+    /// Requests the server to stop accepting connections and drain.
+    pub fn shutdown(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+
+        // a throwaway connection wakes the accept loop so it sees the flag
+        let listener = *self.lock_listener();
+        if let Some(addr) = listener {
+            let _ = TcpStream::connect(addr);
+        }
+    }
+
+    #[must_use]
+    pub fn is_shutdown(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+
+    /// Remembers the address of the listener to wake on shutdown. Must be
+    /// called before the server checks the flag and blocks in `accept`.
+    pub(super) fn wake_on_shutdown(&self, mut addr: SocketAddr) {
+        // a listener on all interfaces is reachable through loopback
+        if addr.ip().is_unspecified() {
+            addr.set_ip(if addr.is_ipv4() {
+                Ipv4Addr::LOCALHOST.into()
+            } else {
+                Ipv6Addr::LOCALHOST.into()
+            });
+        }
+        *self.lock_listener() = Some(addr);
+    }
+
+    fn lock_listener(&self) -> std::sync::MutexGuard<'_, Option<SocketAddr>> {
+        self.listener
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Keeps track of keep-alive connections that are waiting for their next
+/// request so they can be closed when the server shuts down.
+#[derive(Debug, Default)]
+pub struct IdleConnections {
+    next_id: AtomicUsize,
+    streams: Mutex<HashMap<usize, TcpStream>>,
+}
+
+impl IdleConnections {
+    /// Registers a connection as idle and returns the id used to unregister it.
+    /// Returns None if the stream can't be cloned.
+    pub fn register(&self, stream: &TcpStream) -> Option<usize> {
+        let clone = stream.try_clone().ok()?;
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.lock().insert(id, clone);
+        Some(id)
+    }
+
+    pub fn unregister(&self, id: usize) {
+        self.lock().remove(&id);
+    }
+
+    /// Closes every connection currently waiting for a request, which unblocks
+    /// the workers reading from them.
+    pub fn close_all(&self) {
+        for (_, stream) in self.lock().drain() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<usize, TcpStream>> {
+        self.streams
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
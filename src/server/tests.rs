@@ -0,0 +1,502 @@
+use super::timeout::{Phase, TimedStream, Timeouts};
+use super::*;
+use crate::middleware::ConnectionManager;
+use std::io::{ErrorKind, Read, Write};
+use std::net::SocketAddr;
+use std::thread::JoinHandle;
+
+/// A server running on its own thread.
+struct Running {
+    addr: SocketAddr,
+    shutdown: ShutdownHandle,
+    thread: JoinHandle<Result<()>>,
+}
+
+impl Running {
+    /// Requests shutdown and waits for `run` to return, giving up after
+    /// `limit`.
+    fn stop(self, limit: Duration) -> Result<()> {
+        self.shutdown.shutdown();
+        let start = Instant::now();
+        while !self.thread.is_finished() {
+            assert!(
+                start.elapsed() < limit,
+                "server didn't stop within {limit:?}"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        self.thread.join().expect("server thread panicked")
+    }
+}
+
+fn start(config: ServerConfig) -> Running {
+    let server = HttpServer::builder()
+        .config(config)
+        .unwrap()
+        .middleware(ConnectionManager)
+        .echo()
+        .unwrap()
+        .build();
+    start_server(server)
+}
+
+fn start_server(server: HttpServer) -> Running {
+    let listener = server.bind().unwrap();
+    let addr = listener.local_addr().unwrap();
+    let shutdown = server.shutdown_handle();
+    let thread = thread::spawn(move || server.serve(listener));
+
+    Running {
+        addr,
+        shutdown,
+        thread,
+    }
+}
+
+fn test_config() -> ServerConfig {
+    ServerConfig {
+        port: 0,
+        drain_timeout: Duration::from_secs(5),
+        ..ServerConfig::default()
+    }
+}
+
+fn connect(addr: SocketAddr) -> TcpStream {
+    let stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    stream
+}
+
+/// Reads one response with a Content-Length framed body.
+fn read_response(stream: &mut TcpStream) -> String {
+    let mut data = Vec::new();
+    let mut byte = [0; 1];
+    while !data.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte).unwrap() == 0 {
+            break;
+        }
+        data.push(byte[0]);
+    }
+
+    let head = String::from_utf8(data).unwrap();
+    let length = head
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length: "))
+        .map_or(0, |v| v.parse().unwrap());
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).unwrap();
+
+    head + &String::from_utf8(body).unwrap()
+}
+
+/// Returns true once the peer has closed the connection.
+fn is_closed(stream: &mut TcpStream) -> bool {
+    let mut buf = [0; 1];
+    matches!(stream.read(&mut buf), Ok(0))
+}
+
+#[test]
+fn test_serves_on_os_assigned_port() {
+    let server = start(test_config());
+    assert_ne!(server.addr.port(), 0);
+
+    let mut stream = connect(server.addr);
+    stream
+        .write_all(b"GET /echo/hi HTTP/1.1\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let response = read_response(&mut stream);
+
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("\r\n\r\nhi"));
+    server.stop(Duration::from_secs(2)).unwrap();
+}
+
+#[test]
+fn test_binds_configured_address() {
+    let server = start(test_config());
+    assert_eq!(server.addr.ip().to_string(), "127.0.0.1");
+    server.stop(Duration::from_secs(2)).unwrap();
+
+    let config = ServerConfig {
+        bind: String::from("not an address"),
+        ..test_config()
+    };
+    let server = HttpServer::new(Router::new(), config).unwrap();
+    assert!(server.bind().is_err());
+}
+
+#[test]
+fn test_zero_timeouts_are_rejected() {
+    let configs = [
+        ServerConfig {
+            keep_alive_timeout: Duration::ZERO,
+            ..test_config()
+        },
+        ServerConfig {
+            header_timeout: Duration::ZERO,
+            ..test_config()
+        },
+        ServerConfig {
+            body_timeout: Duration::ZERO,
+            ..test_config()
+        },
+    ];
+    for config in configs {
+        assert!(config.validate().is_err());
+        assert!(HttpServer::builder().config(config.clone()).is_err());
+        assert!(HttpServer::new(Router::new(), config).is_err());
+    }
+
+    assert!(timeout_config().validate().is_ok());
+}
+
+#[test]
+fn test_worker_count_bounds_concurrent_connections() {
+    let server = start(ServerConfig {
+        workers: 1,
+        ..test_config()
+    });
+
+    // the only worker stays busy with a keep-alive connection
+    let mut first = connect(server.addr);
+    first.write_all(b"GET /echo/a HTTP/1.1\r\n\r\n").unwrap();
+    assert!(read_response(&mut first).ends_with("\r\n\r\na"));
+
+    let mut second = connect(server.addr);
+    second.write_all(b"GET /echo/b HTTP/1.1\r\n\r\n").unwrap();
+    second
+        .set_read_timeout(Some(Duration::from_millis(200)))
+        .unwrap();
+    let mut buf = [0; 1];
+    assert!(
+        second.read(&mut buf).is_err(),
+        "second connection was served"
+    );
+
+    // once the first connection is gone the worker picks up the second
+    drop(first);
+    second
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    assert!(read_response(&mut second).ends_with("\r\n\r\nb"));
+
+    drop(second);
+    server.stop(Duration::from_secs(2)).unwrap();
+}
+
+#[test]
+fn test_shutdown_closes_idle_connections() {
+    let server = start(test_config());
+
+    let mut stream = connect(server.addr);
+    stream.write_all(b"GET /echo/hi HTTP/1.1\r\n\r\n").unwrap();
+    let response = read_response(&mut stream);
+    assert!(response.contains("connection: keep-alive\r\n"));
+
+    // the connection waits for its next request, so it doesn't hold up the
+    // drain
+    server.stop(Duration::from_secs(2)).unwrap();
+    assert!(is_closed(&mut stream));
+}
+
+#[test]
+fn test_drain_timeout_bounds_shutdown() {
+    let server = start(ServerConfig {
+        drain_timeout: Duration::from_millis(200),
+        ..test_config()
+    });
+
+    // a request that never completes keeps its worker busy
+    let mut stream = connect(server.addr);
+    stream.write_all(b"GET /echo/hi HTTP/1.1\r\n").unwrap();
+    thread::sleep(Duration::from_millis(100));
+
+    server.stop(Duration::from_secs(2)).unwrap();
+}
+
+#[test]
+fn test_shutdown_lets_started_request_finish() {
+    let server = start(test_config());
+
+    // the head arrives in two parts with a drain in between
+    let mut stream = connect(server.addr);
+    stream.write_all(b"GET /echo/hi HTTP/1.1\r\n").unwrap();
+    thread::sleep(Duration::from_millis(100));
+    server.shutdown.shutdown();
+    // a few sweeps of the drain loop pass before the request goes on
+    thread::sleep(Duration::from_millis(200));
+    stream.write_all(b"Content-Length: 4\r\n\r\nda").unwrap();
+    thread::sleep(Duration::from_millis(200));
+    stream.write_all(b"ta").unwrap();
+
+    let response = read_response(&mut stream);
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("connection: close\r\n"));
+    assert!(response.ends_with("\r\n\r\nhi"));
+    server.stop(Duration::from_secs(2)).unwrap();
+}
+
+/// Returns both ends of a loopback TCP connection.
+fn socket_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    (client, server)
+}
+
+const TEST_TIMEOUTS: Timeouts = Timeouts {
+    idle: Duration::from_millis(200),
+    headers: Duration::from_millis(300),
+    body: Duration::from_millis(300),
+    min_body_rate: 0,
+};
+
+/// Sends a byte every `interval` until the peer goes away or `count` bytes
+/// are sent.
+fn trickle(mut stream: TcpStream, interval: Duration, count: usize) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for _ in 0..count {
+            if stream.write_all(b"a").is_err() {
+                return;
+            }
+            thread::sleep(interval);
+        }
+    })
+}
+
+/// Reads until an error and returns it with the time it took.
+fn read_until_error(stream: &mut TimedStream) -> (std::io::Error, Duration) {
+    let start = Instant::now();
+    let mut buf = [0; 16];
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => panic!("the peer closed the connection"),
+            Ok(_) => {}
+            Err(e) => return (e, start.elapsed()),
+        }
+    }
+}
+
+#[test]
+fn test_timed_stream_idle_timeout() {
+    let (_client, server) = socket_pair();
+    let mut stream = TimedStream::new(server, TEST_TIMEOUTS);
+
+    let (e, elapsed) = read_until_error(&mut stream);
+
+    assert_eq!(e.kind(), ErrorKind::TimedOut);
+    assert_eq!(stream.phase(), Phase::Idle);
+    assert!(elapsed >= TEST_TIMEOUTS.idle);
+}
+
+#[test]
+fn test_timed_stream_header_deadline() {
+    let (client, server) = socket_pair();
+    let mut stream = TimedStream::new(server, TEST_TIMEOUTS);
+
+    // every byte arrives well within the idle timeout, but the header
+    // section as a whole takes too long
+    let sender = trickle(client, Duration::from_millis(50), 100);
+    let (e, elapsed) = read_until_error(&mut stream);
+
+    assert_eq!(e.kind(), ErrorKind::TimedOut);
+    assert_eq!(stream.phase(), Phase::Headers);
+    assert!(elapsed < Duration::from_secs(2));
+    drop(stream);
+    sender.join().unwrap();
+}
+
+#[test]
+fn test_timed_stream_body_deadline() {
+    let (client, server) = socket_pair();
+    let mut stream = TimedStream::new(server, TEST_TIMEOUTS);
+    stream.start_body();
+
+    let sender = trickle(client, Duration::from_millis(50), 100);
+    let (e, elapsed) = read_until_error(&mut stream);
+
+    assert_eq!(e.kind(), ErrorKind::TimedOut);
+    assert_eq!(stream.phase(), Phase::Body);
+    assert!(elapsed < Duration::from_secs(2));
+    drop(stream);
+    sender.join().unwrap();
+}
+
+#[test]
+fn test_timed_stream_body_rate_extends_deadline() {
+    let (mut client, server) = socket_pair();
+    let mut stream = TimedStream::new(
+        server,
+        Timeouts {
+            min_body_rate: 10,
+            ..TEST_TIMEOUTS
+        },
+    );
+    stream.start_body();
+
+    // 10 bytes every 100ms is ten times the minimum rate, so the body takes
+    // longer than the body timeout but still arrives
+    let sender = thread::spawn(move || {
+        for _ in 0..6 {
+            client.write_all(&[b'a'; 10]).unwrap();
+            thread::sleep(Duration::from_millis(100));
+        }
+        client
+    });
+    let mut body = [0; 60];
+    stream.read_exact(&mut body).unwrap();
+
+    assert_eq!(body, [b'a'; 60]);
+    drop(sender.join().unwrap());
+}
+
+fn timeout_config() -> ServerConfig {
+    ServerConfig {
+        keep_alive_timeout: TEST_TIMEOUTS.idle,
+        header_timeout: TEST_TIMEOUTS.headers,
+        body_timeout: TEST_TIMEOUTS.body,
+        min_body_rate: 0,
+        ..test_config()
+    }
+}
+
+#[test]
+fn test_slow_headers_get_408() {
+    let server = start(timeout_config());
+
+    let mut stream = connect(server.addr);
+    stream.write_all(b"GET /echo/hi HTTP/1.1\r\n").unwrap();
+    let response = read_response(&mut stream);
+
+    assert!(response.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+    assert!(response.contains("connection: close\r\n"));
+    assert!(is_closed(&mut stream));
+    server.stop(Duration::from_secs(2)).unwrap();
+}
+
+#[test]
+fn test_slow_body_gets_408() {
+    let server = start(timeout_config());
+
+    let mut stream = connect(server.addr);
+    stream
+        .write_all(b"GET /echo/hi HTTP/1.1\r\nContent-Length: 10\r\n\r\nabc")
+        .unwrap();
+    let response = read_response(&mut stream);
+
+    assert!(response.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+    assert!(is_closed(&mut stream));
+    server.stop(Duration::from_secs(2)).unwrap();
+}
+
+#[test]
+fn test_idle_timeout_closes_silently() {
+    let server = start(timeout_config());
+
+    let mut stream = connect(server.addr);
+    stream.write_all(b"GET /echo/hi HTTP/1.1\r\n\r\n").unwrap();
+    let response = read_response(&mut stream);
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    // a sub-second timeout is rounded up rather than advertised as 0
+    assert!(response.contains("keep-alive: timeout=1\r\n"));
+
+    // nothing is sent when the connection is closed for being idle
+    assert!(is_closed(&mut stream));
+    server.stop(Duration::from_secs(2)).unwrap();
+}
+
+#[test]
+fn test_keep_alive_header_advertises_idle_timeout() {
+    let server = start(test_config());
+
+    let mut stream = connect(server.addr);
+    stream.write_all(b"GET /echo/hi HTTP/1.1\r\n\r\n").unwrap();
+    let response = read_response(&mut stream);
+    assert!(response.contains("keep-alive: timeout=5\r\n"));
+
+    // a response that closes the connection doesn't advertise a timeout
+    stream
+        .write_all(b"GET /echo/hi HTTP/1.1\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let response = read_response(&mut stream);
+    assert!(!response.contains("keep-alive:"));
+
+    drop(stream);
+    server.stop(Duration::from_secs(2)).unwrap();
+}
+
+#[test]
+fn test_client_close_without_connection_manager() {
+    let server = HttpServer::builder()
+        .config(test_config())
+        .unwrap()
+        .echo()
+        .unwrap()
+        .build();
+    let server = start_server(server);
+
+    let mut stream = connect(server.addr);
+    stream.write_all(b"GET /echo/a HTTP/1.1\r\n\r\n").unwrap();
+    let response = read_response(&mut stream);
+    assert!(response.contains("connection: keep-alive\r\n"));
+
+    stream
+        .write_all(b"GET /echo/b HTTP/1.1\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let response = read_response(&mut stream);
+    assert!(response.contains("connection: close\r\n"));
+    assert!(!response.contains("keep-alive: "));
+    assert!(is_closed(&mut stream));
+
+    server.stop(Duration::from_secs(2)).unwrap();
+}
+
+#[test]
+fn test_shutdown_without_connection_manager_closes_unannounced() {
+    let server = HttpServer::builder()
+        .config(test_config())
+        .unwrap()
+        .echo()
+        .unwrap()
+        .build();
+    let server = start_server(server);
+
+    let mut stream = connect(server.addr);
+    stream.write_all(b"GET /echo/hi HTTP/1.1\r\n").unwrap();
+    thread::sleep(Duration::from_millis(100));
+    server.shutdown.shutdown();
+    stream.write_all(b"\r\n").unwrap();
+
+    let response = read_response(&mut stream);
+    assert!(response.contains("connection: keep-alive\r\n"));
+    assert!(is_closed(&mut stream));
+    server.stop(Duration::from_secs(2)).unwrap();
+}
+
+#[test]
+fn test_bad_framing_is_answered() {
+    let server = start(test_config());
+
+    let mut stream = connect(server.addr);
+    stream
+        .write_all(
+            b"POST /echo/hi HTTP/1.1\r\nContent-Length: 1\r\nTransfer-Encoding: chunked\r\n\r\n",
+        )
+        .unwrap();
+    let response = read_response(&mut stream);
+    assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    assert!(is_closed(&mut stream));
+
+    let mut stream = connect(server.addr);
+    stream
+        .write_all(b"POST /echo/hi HTTP/1.1\r\nTransfer-Encoding: gzip\r\n\r\n")
+        .unwrap();
+    let response = read_response(&mut stream);
+    assert!(response.starts_with("HTTP/1.1 501 Not Implemented\r\n"));
+    assert!(is_closed(&mut stream));
+
+    server.stop(Duration::from_secs(2)).unwrap();
+}
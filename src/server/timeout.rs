@@ -0,0 +1,158 @@
+/*
+ * This module provides the socket wrapper the server reads requests through,
+ * which bounds how long a client may take to send each part of a request.
+ */
+
+use std::io::{self, ErrorKind, Read};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+/// The part of a request a connection is waiting for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Between requests, before any byte of the next one has arrived.
+    Idle,
+    /// Reading the request line and header section.
+    Headers,
+    /// Reading the request body.
+    Body,
+}
+
+/// How long a connection may wait in each phase.
+#[derive(Debug, Clone, Copy)]
+pub struct Timeouts {
+    /// How long a keep-alive connection may sit idle between requests.
+    pub idle: Duration,
+    /// How long a client has to send the whole header section, counted from
+    /// its first byte.
+    pub headers: Duration,
+    /// How long a client has to send the body, counted from the end of the
+    /// header section.
+    pub body: Duration,
+    /// Bytes per second of body that extend the body timeout by a second
+    /// each, so a large body sent at a reasonable rate still makes it. Zero
+    /// turns the extension off.
+    pub min_body_rate: u64,
+}
+
+/// A `TcpStream` whose reads fail with `ErrorKind::TimedOut` once the
+/// timeout of the current phase runs out.
+pub struct TimedStream {
+    stream: TcpStream,
+    timeouts: Timeouts,
+    phase: Phase,
+    // when the current phase has to be complete
+    deadline: Instant,
+    // how much of the body has arrived
+    body_received: u64,
+    // the read timeout currently set on the socket
+    current: Option<Duration>,
+}
+
+impl TimedStream {
+    pub fn new(stream: TcpStream, timeouts: Timeouts) -> Self {
+        Self {
+            stream,
+            timeouts,
+            phase: Phase::Idle,
+            deadline: Instant::now(),
+            body_received: 0,
+            current: None,
+        }
+    }
+
+    /// Returns the socket, e.g. to write a response to it.
+    pub const fn get_mut(&mut self) -> &mut TcpStream {
+        &mut self.stream
+    }
+
+    pub const fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Starts waiting for the next request. The header timeout starts with
+    /// the first byte that arrives.
+    pub const fn await_request(&mut self) {
+        self.phase = Phase::Idle;
+    }
+
+    /// Switches to the body timeout once the header section has been read.
+    pub fn start_body(&mut self) {
+        self.phase = Phase::Body;
+        self.deadline = Instant::now() + self.timeouts.body;
+        self.body_received = 0;
+    }
+
+    fn timeout(&self) -> io::Result<Duration> {
+        if self.phase == Phase::Idle {
+            return Ok(self.timeouts.idle);
+        }
+
+        // the header section and body have to be complete by a deadline, so
+        // trickling in a byte at a time doesn't keep the connection open
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(self.timed_out());
+        }
+        Ok(remaining)
+    }
+
+    /// Moves the body deadline for `n` more bytes received.
+    fn extend_body_deadline(&mut self, n: usize) {
+        let rate = self.timeouts.min_body_rate;
+        if rate == 0 {
+            return;
+        }
+
+        // extend by whole seconds, so the remainder carries over
+        let before = self.body_received / rate;
+        self.body_received += n as u64;
+        let after = self.body_received / rate;
+        self.deadline += Duration::from_secs(after - before);
+    }
+
+    fn timed_out(&self) -> io::Error {
+        let message = match self.phase {
+            Phase::Idle => format!("idle for {:?}", self.timeouts.idle),
+            Phase::Headers => format!("headers not received within {:?}", self.timeouts.headers),
+            Phase::Body => format!("body not received within {:?}", self.timeouts.body),
+        };
+        io::Error::new(ErrorKind::TimedOut, message)
+    }
+}
+
+impl Read for TimedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let timeout = self.timeout()?;
+        if self.current != Some(timeout) {
+            self.stream.set_read_timeout(Some(timeout))?;
+            self.current = Some(timeout);
+        }
+
+        match self.stream.read(buf) {
+            Ok(n) => {
+                match self.phase {
+                    Phase::Idle if n > 0 => {
+                        self.phase = Phase::Headers;
+                        self.deadline = Instant::now() + self.timeouts.headers;
+                    }
+                    Phase::Body => self.extend_body_deadline(n),
+                    _ => {}
+                }
+                Ok(n)
+            }
+            // the socket reports an expired read timeout as either kind,
+            // depending on the platform
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                Err(self.timed_out())
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Returns true if `e` was caused by a read timeout of a `TimedStream`.
+pub fn is_timeout(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == ErrorKind::TimedOut)
+}